@@ -1,6 +1,26 @@
-use std::collections::HashMap;
+use std::{
+    any::{Any, type_name},
+    collections::HashMap,
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Weak,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
 
-use crate::{ReadOnlyString, graphics::image::Image};
+use parking_lot::{Mutex, RwLock};
+use thiserror::Error;
+
+use crate::{
+    ReadOnlyString,
+    component::ComponentHandle,
+    graphics::{
+        image::Image,
+        lowlevel::{WgpuRenderer, shader::ShaderError},
+    },
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct AssetStore {
@@ -31,3 +51,574 @@ impl AssetStore {
         self.images.get(name).cloned()
     }
 }
+
+/// An error that occurred while loading an asset.
+#[derive(Debug, Error)]
+pub enum AssetError {
+    #[error("no decoder is registered for the extension of {0}")]
+    NoDecoder(PathBuf),
+    #[error("the decoder for {path} does not produce {expected}")]
+    TypeMismatch {
+        path: PathBuf,
+        expected: &'static str,
+    },
+    #[error("failed to read asset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to decode asset: {0}")]
+    Decode(anyhow::Error),
+    #[error("failed to compile shader: {0}")]
+    Shader(#[from] ShaderError),
+}
+
+/// The state of an asset loaded through an [`AssetServer`].
+#[derive(Debug)]
+pub enum AssetState<T> {
+    /// The asset is still being read or decoded.
+    Loading,
+    /// The asset was loaded successfully.
+    Loaded(Arc<T>),
+    /// The asset failed to load.
+    Failed(Arc<AssetError>),
+}
+
+// derive(Clone) would require T: Clone
+impl<T> Clone for AssetState<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Loading => Self::Loading,
+            Self::Loaded(asset) => Self::Loaded(asset.clone()),
+            Self::Failed(err) => Self::Failed(err.clone()),
+        }
+    }
+}
+
+/// A handle to an asset that is loaded in the background.
+///
+/// The handle starts out as [`AssetState::Loading`] and is updated once the load task finishes, so it can be polled
+/// every frame without blocking.
+pub struct AssetHandle<T> {
+    path: Arc<Path>,
+    state: Arc<RwLock<AssetState<T>>>,
+    reload_error: Arc<RwLock<Option<Arc<AssetError>>>>,
+}
+
+impl<T> AssetHandle<T> {
+    /// Returns the path the asset is loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the current state of the asset.
+    pub fn state(&self) -> AssetState<T> {
+        self.state.read().clone()
+    }
+
+    /// Returns the asset if it has finished loading.
+    pub fn get(&self) -> Option<Arc<T>> {
+        match &*self.state.read() {
+            AssetState::Loaded(asset) => Some(asset.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the error if the asset failed to load.
+    pub fn error(&self) -> Option<Arc<AssetError>> {
+        match &*self.state.read() {
+            AssetState::Failed(err) => Some(err.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the error from the most recent reload of a watched asset, if it failed.
+    ///
+    /// A failed reload keeps the last good asset in place, so this is how a broken edit is surfaced. It is cleared
+    /// by the next successful reload. See [`AssetServer::watch`].
+    pub fn last_reload_error(&self) -> Option<Arc<AssetError>> {
+        self.reload_error.read().clone()
+    }
+
+    /// Returns true if the asset is still loading.
+    pub fn is_loading(&self) -> bool {
+        matches!(*self.state.read(), AssetState::Loading)
+    }
+
+    /// Returns true if the asset has finished loading, whether it succeeded or not.
+    pub fn is_done(&self) -> bool {
+        !self.is_loading()
+    }
+}
+
+impl<T> Clone for AssetHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            state: self.state.clone(),
+            reload_error: self.reload_error.clone(),
+        }
+    }
+}
+
+impl<T> Debug for AssetHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match &*self.state.read() {
+            AssetState::Loading => "Loading",
+            AssetState::Loaded(_) => "Loaded",
+            AssetState::Failed(_) => "Failed",
+        };
+        f.debug_struct("AssetHandle")
+            .field("path", &self.path)
+            .field("state", &state)
+            .finish()
+    }
+}
+
+type ErasedAsset = Arc<dyn Any + Send + Sync>;
+type Decoder = Arc<dyn Fn(&[u8]) -> anyhow::Result<ErasedAsset> + Send + Sync>;
+/// Swaps a reloaded asset (or the error it failed with) into a handle. Returns false once the handle has been dropped.
+type Reloader = Box<dyn Fn(&Result<ErasedAsset, Arc<AssetError>>) -> bool + Send>;
+type WatchMap = HashMap<Arc<Path>, WatchedAsset>;
+
+/// How often watched assets are checked for changes.
+///
+/// Changes are found by polling modification times rather than through a native file notification backend. That
+/// needs no extra dependency, behaves the same on every platform (including network and container mounts, where
+/// native events are unreliable), and a handful of watched files costs one `stat` each per interval.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+struct WatchedAsset {
+    modified: Option<SystemTime>,
+    reloaders: Vec<Reloader>,
+}
+
+/// Loads assets from disk on the `smol` executor.
+///
+/// Decoders are registered per file extension, and [`AssetServer::load`] picks the decoder based on the extension of
+/// the path. Reading and decoding both happen off the calling thread.
+#[derive(Clone)]
+pub struct AssetServer {
+    decoders: Arc<RwLock<HashMap<ReadOnlyString, Decoder>>>,
+    pending: Arc<AtomicUsize>,
+    watched: Arc<Mutex<WatchMap>>,
+    watching: Arc<AtomicBool>,
+}
+
+impl AssetServer {
+    /// Creates a new asset server with decoders for common image formats.
+    pub fn new() -> Self {
+        let server = Self::empty();
+        for ext in ["png", "jpg", "jpeg", "bmp", "gif", "tga"] {
+            server.register_decoder(ext, |bytes| Ok(Image::from_mem(bytes)?));
+        }
+        server
+    }
+
+    /// Creates a new asset server without any decoders.
+    pub fn empty() -> Self {
+        Self {
+            decoders: Arc::new(RwLock::new(HashMap::new())),
+            pending: Arc::new(AtomicUsize::new(0)),
+            watched: Arc::new(Mutex::new(HashMap::new())),
+            watching: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Registers a decoder for `.wgsl` files that compiles them into a [`ShaderProgram`] with `wgpu`.
+    ///
+    /// Shaders go through [`WgpuRenderer::try_load_shader`], so includes are resolved and the module is validated. A
+    /// shader that fails to compile ends up in [`AssetState::Failed`] with [`AssetError::Shader`], including when a
+    /// watched shader is edited into a broken state.
+    ///
+    /// [`ShaderProgram`]: crate::graphics::lowlevel::shader::ShaderProgram
+    pub fn register_shader_decoder(&self, wgpu: ComponentHandle<WgpuRenderer>) {
+        self.register_decoder("wgsl", move |bytes| {
+            let source = std::str::from_utf8(bytes)?;
+            Ok(wgpu.get().try_load_shader(source, None, None, None)?)
+        });
+    }
+
+    /// Registers `decoder` for files with the extension `ext`, replacing any previous decoder for that extension.
+    ///
+    /// Extensions are matched case-insensitively and without the leading dot.
+    pub fn register_decoder<T, F>(&self, ext: &str, decoder: F)
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&[u8]) -> anyhow::Result<T> + Send + Sync + 'static,
+    {
+        let decoder: Decoder = Arc::new(move |bytes| Ok(Arc::new(decoder(bytes)?) as ErasedAsset));
+        self.decoders
+            .write()
+            .insert(ext.trim_start_matches('.').to_lowercase().into(), decoder);
+    }
+
+    /// Returns true if a decoder is registered for `ext`.
+    pub fn has_decoder(&self, ext: &str) -> bool {
+        self.decoders
+            .read()
+            .contains_key(ext.trim_start_matches('.').to_lowercase().as_str())
+    }
+
+    /// Starts loading the asset at `path` in the background and returns a handle to it.
+    ///
+    /// If the decoder for the extension of `path` does not produce a `T`, the handle will end up in
+    /// [`AssetState::Failed`].
+    pub fn load<T: Send + Sync + 'static>(&self, path: impl AsRef<Path>) -> AssetHandle<T> {
+        let path: Arc<Path> = path.as_ref().into();
+        let handle = AssetHandle {
+            path: path.clone(),
+            state: Arc::new(RwLock::new(AssetState::Loading)),
+            reload_error: Arc::new(RwLock::new(None)),
+        };
+
+        let Some(decoder) = self.decoder_for(&path) else {
+            *handle.state.write() =
+                AssetState::Failed(Arc::new(AssetError::NoDecoder(path.to_path_buf())));
+            return handle;
+        };
+
+        let state = handle.state.clone();
+        let pending = self.pending.clone();
+        pending.fetch_add(1, Ordering::Relaxed);
+        smol::spawn(async move {
+            let result = load_asset::<T>(&path, decoder).await;
+            *state.write() = match result {
+                Ok(asset) => AssetState::Loaded(asset),
+                Err(err) => {
+                    log::error!("Failed to load asset {}: {}", path.display(), err);
+                    AssetState::Failed(Arc::new(err))
+                }
+            };
+            pending.fetch_sub(1, Ordering::Relaxed);
+        })
+        .detach();
+
+        handle
+    }
+
+    /// Loads the asset at `path` like [`AssetServer::load`], and reloads it whenever the file changes on disk.
+    ///
+    /// Reloaded assets are swapped in behind every handle watching `path`, so holders see the new value on their
+    /// next [`AssetHandle::get`]. If a reload fails to read or decode, the error is logged and exposed through
+    /// [`AssetHandle::last_reload_error`] until the next successful reload, while `get` keeps returning the last good
+    /// asset.
+    ///
+    /// The file is polled for changes every [`WATCH_INTERVAL`].
+    pub fn watch<T: Send + Sync + 'static>(&self, path: impl AsRef<Path>) -> AssetHandle<T> {
+        let handle = self.load::<T>(path);
+        if handle.error().is_some() {
+            return handle;
+        }
+
+        let weak = Arc::downgrade(&handle.state);
+        let reload_error = Arc::downgrade(&handle.reload_error);
+        let path = handle.path.clone();
+        let reloader: Reloader = Box::new(move |result| {
+            let (Some(state), Some(reload_error)) = (weak.upgrade(), reload_error.upgrade()) else {
+                return false;
+            };
+            let result = result.as_ref().map_err(Arc::clone).and_then(|asset| {
+                asset.clone().downcast::<T>().map_err(|_| {
+                    Arc::new(AssetError::TypeMismatch {
+                        path: path.to_path_buf(),
+                        expected: type_name::<T>(),
+                    })
+                })
+            });
+            match result {
+                Ok(asset) => {
+                    *state.write() = AssetState::Loaded(asset);
+                    *reload_error.write() = None;
+                }
+                Err(err) => {
+                    // keep the last good asset, so `get` doesn't start failing mid-session
+                    let mut state = state.write();
+                    if !matches!(*state, AssetState::Loaded(_)) {
+                        *state = AssetState::Failed(err.clone());
+                    }
+                    *reload_error.write() = Some(err);
+                }
+            }
+            true
+        });
+
+        self.watched
+            .lock()
+            .entry(handle.path.clone())
+            .or_insert_with(|| WatchedAsset {
+                modified: modified_time(&handle.path),
+                reloaders: Vec::new(),
+            })
+            .reloaders
+            .push(reloader);
+
+        if !self.watching.swap(true, Ordering::AcqRel) {
+            smol::spawn(watch_assets(
+                Arc::downgrade(&self.watched),
+                self.decoders.clone(),
+            ))
+            .detach();
+        }
+
+        handle
+    }
+
+    /// Returns the number of paths that are being watched for changes.
+    pub fn watched_count(&self) -> usize {
+        self.watched.lock().len()
+    }
+
+    /// Returns the number of assets that are still loading.
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    fn decoder_for(&self, path: &Path) -> Option<Decoder> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        self.decoders.read().get(ext.as_str()).cloned()
+    }
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for AssetServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssetServer")
+            .field(
+                "extensions",
+                &self.decoders.read().keys().collect::<Vec<_>>(),
+            )
+            .field("pending", &self.pending())
+            .field("watched", &self.watched_count())
+            .finish()
+    }
+}
+
+async fn decode_asset(path: &Path, decoder: Decoder) -> Result<ErasedAsset, AssetError> {
+    let bytes = smol::fs::read(path).await?;
+    // decoding is CPU bound, so keep it off the executor threads
+    smol::unblock(move || decoder(&bytes)).await.map_err(|err| {
+        match err.downcast::<ShaderError>() {
+            Ok(err) => AssetError::Shader(err),
+            Err(err) => AssetError::Decode(err),
+        }
+    })
+}
+
+async fn load_asset<T: Send + Sync + 'static>(
+    path: &Path,
+    decoder: Decoder,
+) -> Result<Arc<T>, AssetError> {
+    let asset = decode_asset(path, decoder).await?;
+    asset.downcast::<T>().map_err(|_| AssetError::TypeMismatch {
+        path: path.to_path_buf(),
+        expected: type_name::<T>(),
+    })
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// Polls the modification time of every watched asset, reloading the ones that changed.
+///
+/// Exits once the owning [`AssetServer`] (and all of its clones) have been dropped.
+async fn watch_assets(
+    watched: Weak<Mutex<WatchMap>>,
+    decoders: Arc<RwLock<HashMap<ReadOnlyString, Decoder>>>,
+) {
+    loop {
+        smol::Timer::after(WATCH_INTERVAL).await;
+        let Some(watched) = watched.upgrade() else {
+            return;
+        };
+
+        let changed = watched
+            .lock()
+            .iter_mut()
+            .filter_map(|(path, asset)| {
+                let modified = modified_time(path);
+                (modified != asset.modified).then(|| {
+                    asset.modified = modified;
+                    path.clone()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for path in changed {
+            let decoder = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(|ext| decoders.read().get(ext.to_lowercase().as_str()).cloned());
+            let Some(decoder) = decoder else {
+                continue;
+            };
+
+            let result = decode_asset(&path, decoder).await.map_err(Arc::new);
+            match &result {
+                Ok(_) => log::info!("Reloaded asset {}", path.display()),
+                Err(err) => log::error!("Failed to reload asset {}: {}", path.display(), err),
+            }
+
+            let mut watched = watched.lock();
+            if let Some(entry) = watched.get_mut(&path) {
+                entry.reloaders.retain(|reload| reload(&result));
+                if entry.reloaders.is_empty() {
+                    watched.remove(&path);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant, SystemTime};
+
+    use super::{AssetError, AssetHandle, AssetServer};
+    use crate::graphics::lowlevel::shader::ShaderError;
+
+    fn wait<T>(handle: &AssetHandle<T>) {
+        let start = Instant::now();
+        while handle.is_loading() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "asset never loaded"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_load_with_registered_decoder() {
+        let path = std::env::temp_dir().join(format!("kiwi_asset_{}.TXT", std::process::id()));
+        std::fs::write(&path, "hello").unwrap();
+
+        let server = AssetServer::empty();
+        server.register_decoder(".txt", |bytes| Ok(String::from_utf8(bytes.to_vec())?));
+
+        let handle = server.load::<String>(&path);
+        wait(&handle);
+        assert_eq!(handle.get().as_deref().map(String::as_str), Some("hello"));
+
+        // the decoder produces a String, so asking for anything else fails
+        let mismatched = server.load::<u32>(&path);
+        wait(&mismatched);
+        assert!(matches!(
+            mismatched.error().as_deref(),
+            Some(AssetError::TypeMismatch { .. })
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_watch_swaps_reloaded_asset() {
+        let path = std::env::temp_dir().join(format!("kiwi_watch_{}.txt", std::process::id()));
+        std::fs::write(&path, "before").unwrap();
+
+        let server = AssetServer::empty();
+        server.register_decoder("txt", |bytes| Ok(String::from_utf8(bytes.to_vec())?));
+
+        let handle = server.watch::<String>(&path);
+        wait(&handle);
+        assert_eq!(handle.get().as_deref().map(String::as_str), Some("before"));
+        assert_eq!(server.watched_count(), 1);
+
+        std::fs::write(&path, "after").unwrap();
+        // bump the modification time explicitly in case the filesystem has a coarse timestamp resolution
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+
+        let start = Instant::now();
+        while handle.get().as_deref().map(String::as_str) != Some("after") {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "asset never reloaded"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_reload_surfaces_shader_error() {
+        let path = std::env::temp_dir().join(format!("kiwi_shader_{}.wgsl", std::process::id()));
+        std::fs::write(&path, "ok").unwrap();
+
+        // stands in for the real shader decoder, which needs a device
+        let server = AssetServer::empty();
+        server.register_decoder("wgsl", |bytes| match bytes {
+            b"ok" => Ok(1u32),
+            _ => Err(ShaderError::Compilation("broken".into()).into()),
+        });
+
+        let handle = server.watch::<u32>(&path);
+        wait(&handle);
+        assert_eq!(handle.get().as_deref(), Some(&1));
+
+        std::fs::write(&path, "broken").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+
+        let start = Instant::now();
+        while handle.last_reload_error().is_none() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "broken shader never surfaced"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(matches!(
+            handle.last_reload_error().as_deref(),
+            Some(AssetError::Shader(ShaderError::Compilation(_)))
+        ));
+        // the last good asset is kept
+        assert_eq!(handle.get().as_deref(), Some(&1));
+        assert!(handle.error().is_none());
+
+        // fixing the file clears the error
+        std::fs::write(&path, "ok").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(20))
+            .unwrap();
+
+        let start = Instant::now();
+        while handle.last_reload_error().is_some() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "fixed shader never reloaded"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(handle.get().as_deref(), Some(&1));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_without_decoder_fails_immediately() {
+        let server = AssetServer::empty();
+        let handle = server.load::<String>("missing.unknown");
+        assert!(matches!(
+            handle.error().as_deref(),
+            Some(AssetError::NoDecoder(_))
+        ));
+    }
+}
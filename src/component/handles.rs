@@ -1,3 +1,5 @@
+use std::ops::{Deref, DerefMut};
+
 use crate::{
     component::resource::ComponentPtr,
     prelude::{ComponentReadGuard, ComponentWriteGuard},
@@ -11,7 +13,8 @@ pub struct ComponentHandle<T: 'static> {
 
 impl<T> ComponentHandle<T> {
     pub(super) fn new(ptr: ComponentPtr) -> Self {
-        assert!(ptr.is::<T>());
+        // uninitialized pointers have no component to check yet
+        assert!(!ptr.is_initialized() || ptr.is::<T>());
         Self {
             ptr,
             _phantom: std::marker::PhantomData,
@@ -53,6 +56,77 @@ impl<T> ComponentHandle<T> {
     pub fn write(&self) -> ComponentWriteGuard<T> {
         self.ptr.write()
     }
+
+    /// Read locks the component for the duration of `f`. The lock is released before this returns.
+    pub fn with_read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.ptr.with_read(f)
+    }
+
+    /// Write locks the component for the duration of `f`. The lock is released before this returns.
+    #[track_caller]
+    pub fn with_write<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.ptr.with_write(f)
+    }
+
+    /// Returns true if the component has been initialized. See `ComponentStore::insert_uninitialized`.
+    pub fn is_initialized(&self) -> bool {
+        self.ptr.is_initialized()
+    }
+
+    /// Initializes a component created by `ComponentStore::insert_uninitialized`.
+    ///
+    /// Returns false, dropping `component`, if the component was already initialized.
+    pub fn initialize(&self, component: T) -> bool
+    where
+        T: Send + Sync,
+    {
+        self.ptr.clone().initialize(component).is_some()
+    }
+
+    /// Returns true if a write guard was dropped during a panic. See `ComponentPtr::is_poisoned`.
+    pub fn is_poisoned(&self) -> bool {
+        self.ptr.is_poisoned()
+    }
+
+    /// Clears the poisoned state, so the component can be locked normally again. See `ComponentPtr::clear_poison`.
+    pub fn clear_poison(&self) {
+        self.ptr.clear_poison();
+    }
+
+    /// Returns true if the component is currently write locked. This is a racy snapshot, only useful as a heuristic.
+    pub fn is_write_locked(&self) -> bool {
+        self.ptr.is_write_locked()
+    }
+
+    /// Returns the number of read guards currently held. This is a racy snapshot, only useful as a heuristic.
+    pub fn reader_count(&self) -> usize {
+        self.ptr.reader_count()
+    }
+
+    /// Projects the handle onto a part of the component, such as a single field.
+    ///
+    /// The returned handle locks the whole component, but its guards only give access to the part selected by `read`
+    /// and `write`. Function pointers are used rather than closures so the projection is cheap to copy and can't
+    /// capture anything that outlives the lock.
+    pub fn map<U: 'static>(
+        self,
+        read: fn(&T) -> &U,
+        write: fn(&mut T) -> &mut U,
+    ) -> MappedComponentHandle<T, U> {
+        MappedComponentHandle {
+            handle: self,
+            read,
+            write,
+        }
+    }
+
+    /// Replaces the component with `new` under the write lock, returning the old value.
+    #[track_caller]
+    pub fn replace(&self, new: T) -> T {
+        self.ptr
+            .replace(new)
+            .expect("ComponentHandle::replace: Type mismatch when replacing component")
+    }
 }
 
 impl<T> Clone for ComponentHandle<T> {
@@ -71,3 +145,113 @@ impl<T> std::fmt::Debug for ComponentHandle<T> {
             .finish()
     }
 }
+
+/// A handle to part of a component, created by [`ComponentHandle::map`].
+pub struct MappedComponentHandle<T: 'static, U: 'static> {
+    handle: ComponentHandle<T>,
+    read: fn(&T) -> &U,
+    write: fn(&mut T) -> &mut U,
+}
+
+impl<T, U> MappedComponentHandle<T, U> {
+    /// Read locks the parent component and returns a guard to the projected part.
+    pub fn read(&self) -> MappedReadGuard<T, U> {
+        MappedReadGuard {
+            guard: self.handle.read(),
+            project: self.read,
+        }
+    }
+
+    /// Write locks the parent component and returns a guard to the projected part.
+    #[track_caller]
+    pub fn write(&self) -> MappedWriteGuard<T, U> {
+        MappedWriteGuard {
+            guard: self.handle.write(),
+            read: self.read,
+            write: self.write,
+        }
+    }
+
+    /// Returns the handle to the whole component.
+    pub fn parent(&self) -> &ComponentHandle<T> {
+        &self.handle
+    }
+}
+
+impl<T, U> Clone for MappedComponentHandle<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            read: self.read,
+            write: self.write,
+        }
+    }
+}
+
+impl<T, U> std::fmt::Debug for MappedComponentHandle<T, U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedComponentHandle")
+            .field("type", &std::any::type_name::<T>())
+            .field("projected", &std::any::type_name::<U>())
+            .finish()
+    }
+}
+
+/// A read guard to part of a component. The whole component stays read locked until this is dropped.
+pub struct MappedReadGuard<T: 'static, U: 'static> {
+    guard: ComponentReadGuard<T>,
+    project: fn(&T) -> &U,
+}
+
+impl<T, U> Deref for MappedReadGuard<T, U> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        (self.project)(&self.guard)
+    }
+}
+
+/// A write guard to part of a component. The whole component stays write locked until this is dropped.
+pub struct MappedWriteGuard<T: 'static, U: 'static> {
+    guard: ComponentWriteGuard<T>,
+    read: fn(&T) -> &U,
+    write: fn(&mut T) -> &mut U,
+}
+
+impl<T, U> Deref for MappedWriteGuard<T, U> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        (self.read)(&self.guard)
+    }
+}
+
+impl<T, U> DerefMut for MappedWriteGuard<T, U> {
+    fn deref_mut(&mut self) -> &mut U {
+        (self.write)(&mut self.guard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ComponentHandle;
+
+    struct Player {
+        health: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_mapped_handle_projects_field() {
+        let handle = ComponentHandle::standalone(Player {
+            health: 100,
+            name: String::from("kiwi"),
+        });
+        let health = handle.clone().map(|p| &p.health, |p| &mut p.health);
+
+        *health.write() -= 30;
+        assert_eq!(*health.read(), 70);
+        assert_eq!(handle.read().health, 70);
+        assert_eq!(handle.read().name, "kiwi");
+    }
+}
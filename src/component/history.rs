@@ -0,0 +1,196 @@
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+};
+
+use rustc_hash::FxBuildHasher;
+
+use crate::component::resource::ComponentPtr;
+
+/// A point-in-time copy of the tracked components in a `ComponentStore`.
+#[derive(Default)]
+pub struct ComponentSnapshot {
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>, FxBuildHasher>,
+}
+
+impl ComponentSnapshot {
+    /// Retrieves the captured value of a component of the specified type.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|v| v.downcast_ref::<T>())
+    }
+
+    /// Returns the number of components captured in this snapshot.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if no components were captured in this snapshot.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl Debug for ComponentSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComponentSnapshot")
+            .field("components", &self.values.len())
+            .finish()
+    }
+}
+
+/// Type-erased capture/restore functions for a single `Clone` component type.
+#[derive(Clone, Copy)]
+struct Snapshotter {
+    capture: fn(&ComponentPtr) -> Option<Box<dyn Any + Send + Sync>>,
+    restore: fn(&ComponentPtr, &(dyn Any + Send + Sync)),
+}
+
+impl Snapshotter {
+    fn new<T: Clone + Send + Sync + 'static>() -> Self {
+        Self {
+            capture: capture::<T>,
+            restore: restore::<T>,
+        }
+    }
+}
+
+fn capture<T: Clone + Send + Sync + 'static>(
+    ptr: &ComponentPtr,
+) -> Option<Box<dyn Any + Send + Sync>> {
+    let guard = ptr.try_read::<T>().ok()??;
+    Some(Box::new(T::clone(&guard)))
+}
+
+fn restore<T: Clone + Send + Sync + 'static>(ptr: &ComponentPtr, value: &(dyn Any + Send + Sync)) {
+    if let Some(value) = value.downcast_ref::<T>() {
+        *ptr.write::<T>() = value.clone();
+    }
+}
+
+/// A ring buffer of component snapshots, used for stepping backwards while debugging.
+///
+/// Only components registered with `ComponentStore::track_history` participate.
+#[derive(Default)]
+pub(super) struct ComponentHistory {
+    tracked: HashMap<TypeId, Snapshotter, FxBuildHasher>,
+    frames: VecDeque<ComponentSnapshot>,
+}
+
+impl ComponentHistory {
+    /// Registers a component type for snapshotting.
+    pub(super) fn track<T: Clone + Send + Sync + 'static>(&mut self) {
+        self.tracked
+            .insert(TypeId::of::<T>(), Snapshotter::new::<T>());
+    }
+
+    /// Captures a snapshot of all tracked components, evicting the oldest frames beyond `max_frames`.
+    pub(super) fn capture(
+        &mut self,
+        max_frames: usize,
+        lookup: impl Fn(TypeId) -> Option<ComponentPtr>,
+    ) {
+        let mut snapshot = ComponentSnapshot::default();
+        for (id, snapshotter) in &self.tracked {
+            if let Some(value) = lookup(*id).and_then(|ptr| (snapshotter.capture)(&ptr)) {
+                snapshot.values.insert(*id, value);
+            }
+        }
+
+        self.frames.push_back(snapshot);
+        while self.frames.len() > max_frames {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Re-applies the snapshot taken `n` captures ago. `0` is the most recent capture.
+    ///
+    /// Returns false if no such frame exists.
+    pub(super) fn restore(
+        &self,
+        n: usize,
+        lookup: impl Fn(TypeId) -> Option<ComponentPtr>,
+    ) -> bool {
+        let Some(snapshot) = self.frame(n) else {
+            return false;
+        };
+
+        for (id, value) in &snapshot.values {
+            if let (Some(snapshotter), Some(ptr)) = (self.tracked.get(id), lookup(*id)) {
+                (snapshotter.restore)(&ptr, value.as_ref());
+            }
+        }
+        true
+    }
+
+    /// Returns the snapshot taken `n` captures ago. `0` is the most recent capture.
+    pub(super) fn frame(&self, n: usize) -> Option<&ComponentSnapshot> {
+        let index = self.frames.len().checked_sub(n + 1)?;
+        self.frames.get(index)
+    }
+
+    /// Returns the number of captured frames.
+    pub(super) fn len(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl Debug for ComponentHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComponentHistory")
+            .field("tracked", &self.tracked.len())
+            .field("frames", &self.frames.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::component::ComponentStore;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Position(i32, i32);
+
+    #[test]
+    fn test_restore_previous_frame() {
+        let mut store = ComponentStore::new();
+        let handle = store.insert(Position(0, 0));
+        store.finish_initialization();
+        store.track_history::<Position>();
+
+        store.capture_history(8);
+        handle.write().0 = 5;
+        store.capture_history(8);
+        handle.write().1 = 7;
+        store.capture_history(8);
+
+        assert_eq!(store.history_len(), 3);
+        assert_eq!(*handle.read(), Position(5, 7));
+
+        assert!(store.restore_frame(1));
+        assert_eq!(*handle.read(), Position(5, 0));
+
+        assert!(store.restore_frame(2));
+        assert_eq!(*handle.read(), Position(0, 0));
+
+        assert!(!store.restore_frame(3));
+    }
+
+    #[test]
+    fn test_history_evicts_oldest() {
+        let mut store = ComponentStore::new();
+        let handle = store.insert(0u32);
+        store.track_history::<u32>();
+
+        for i in 0..5 {
+            *handle.write() = i;
+            store.capture_history(2);
+        }
+
+        assert_eq!(store.history_len(), 2);
+        assert!(store.restore_frame(1));
+        assert_eq!(*handle.read(), 3);
+    }
+}
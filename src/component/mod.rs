@@ -1,5 +1,5 @@
 use std::{
-    any::TypeId,
+    any::{Any, TypeId},
     collections::HashMap,
     fmt::Debug,
     ops::Deref,
@@ -8,29 +8,57 @@ use std::{
 };
 
 pub mod handles;
+mod history;
 mod resource;
 mod typemap;
 
-pub use handles::ComponentHandle;
+pub use handles::{ComponentHandle, MappedComponentHandle, MappedReadGuard, MappedWriteGuard};
+pub use history::ComponentSnapshot;
 
 pub use typemap::{ImmutableTypeMap, TypeMap};
 
-use parking_lot::{MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadGuard};
+use parking_lot::{Mutex, RwLock};
 //use resource::ResourceNode;
 use rustc_hash::FxBuildHasher;
 
-use crate::component::resource::ComponentPtr;
+use crate::component::history::ComponentHistory;
 
 type ResourceMap = HashMap<TypeId, ComponentPtr, FxBuildHasher>;
 
-pub use resource::{read::ComponentReadGuard, write::ComponentWriteGuard};
+pub use resource::{
+    ComponentPtr, TryLockError, TypeMismatchError,
+    read::{ComponentReadGuard, ReadBarrier},
+    write::ComponentWriteGuard,
+};
+
+/// Returned when a `ComponentStore` is used in a way that requires `finish_initialization` to have been called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("ComponentStore used before finish_initialization")]
+pub struct NotInitialized;
 
 /// A database for storing components of various types.
+///
+/// Components are looked up in the following order:
+/// 1. The finalized map, populated by `finish_initialization`.
+/// 2. The dynamic map, populated by `insert_dynamic`. This is the only way to add components after finalization.
+/// 3. The modification map, used while the store is being initialized.
+///
+/// The store is a single pointer to shared state, so clones are cheap and all refer to the same components.
 #[derive(Clone)]
 pub struct ComponentStore {
+    inner: Arc<StoreInner>,
+}
+
+struct StoreInner {
     /// A modification map used during initialization.
     modification_map: ComponentHandle<ResourceMap>,
-    map: Arc<OnceLock<ResourceMap>>,
+    map: OnceLock<ResourceMap>,
+    /// Components registered at runtime, after the store was finalized.
+    dynamic: RwLock<ResourceMap>,
+    /// Snapshots of `Clone` components, used for stepping backwards while debugging.
+    history: Mutex<ComponentHistory>,
+    /// Every component type in the order it was added, so iteration is deterministic.
+    order: Mutex<Vec<TypeId>>,
 }
 
 pub type ComponentStoreHandle = ComponentStore;
@@ -39,28 +67,132 @@ impl ComponentStore {
     /// Creates a new, empty component database.
     pub fn new() -> Self {
         Self {
-            modification_map: ComponentHandle::standalone(Default::default()),
-            map: Default::default(),
+            inner: Arc::new(StoreInner {
+                modification_map: ComponentHandle::standalone(Default::default()),
+                map: Default::default(),
+                dynamic: Default::default(),
+                history: Default::default(),
+                order: Default::default(),
+            }),
         }
     }
 
     /// Finalizes the initialization of the component database.
     pub fn finish_initialization(&self) {
-        let map = self.modification_map.read().clone();
-        self.map
+        let map = self.inner.modification_map.read().clone();
+        self.inner
+            .map
             .set(map)
             .expect("ComponentDB finish_initialization called multiple times");
     }
 
+    /// Checks that the finalized component map is in place, so lookups of finalized components never take a lock.
+    ///
+    /// This is intended to be called before handing the store to code that should not block, such as a render
+    /// thread. It never finalizes the store itself; if `finish_initialization` has not been called yet, lookups still
+    /// go through the locked modification map and `NotInitialized` is returned.
+    pub fn resolve(&self) -> Result<(), NotInitialized> {
+        self.get_map_checked().map(|_| ())
+    }
+
+    /// Returns true once the store has been finalized by `finish_initialization`.
+    ///
+    /// Code that runs during staged startup can use this to defer work that needs the finalized store.
+    pub fn is_ready(&self) -> bool {
+        self.inner.map.get().is_some()
+    }
+
+    /// Returns an error if the store has not been finalized yet. See `is_ready`.
+    pub fn check_ready(&self) -> Result<(), NotInitialized> {
+        self.get_map_checked().map(|_| ())
+    }
+
+    /// Returns the finalized component map, or an error if the store has not been finalized yet.
+    pub(crate) fn get_map_checked(&self) -> Result<&ResourceMap, NotInitialized> {
+        self.inner.map.get().ok_or(NotInitialized)
+    }
+
+    /// Returns a view of the finalized components that looks them up directly, or an error if the store has not been
+    /// finalized yet.
+    ///
+    /// This is meant for hot loops that read several components each tick. Components added with `insert_dynamic`
+    /// are not part of the finalized map, so they can't be reached through it.
+    pub fn bundle(&self) -> Result<ResolvedStore<'_>, NotInitialized> {
+        self.get_map_checked().map(|map| ResolvedStore { map })
+    }
+
     /// Inserts a component into the database.
     ///
     pub fn insert<T: 'static + Send + Sync>(&mut self, component: T) -> ComponentHandle<T> {
-        if self.map.get().is_some() {
+        let ptr = self.insert_ptr(TypeId::of::<T>(), ComponentPtr::new(component));
+        ComponentHandle::new(ptr)
+    }
+
+    /// Inserts an uninitialized slot for a component of type T, to be filled in later with
+    /// [`ComponentHandle::initialize`].
+    ///
+    /// This allows components with cyclic dependencies on other components to hold handles to each other before
+    /// they are constructed. Reading or writing the component before it is initialized panics.
+    ///
+    /// # Panics
+    /// Panics if the store is finalized, or if a component of type T already exists.
+    pub fn insert_uninitialized<T: 'static + Send + Sync>(&mut self) -> ComponentHandle<T> {
+        let ptr = self.insert_ptr(TypeId::of::<T>(), ComponentPtr::uninitialized::<T>());
+        ComponentHandle::new(ptr)
+    }
+
+    /// Inserts an already boxed component into the database, keeping it in its own allocation.
+    ///
+    /// The component is stored under its concrete type, so a `Box<dyn MyTrait>` upcast to `Box<dyn Any + Send + Sync>`
+    /// is reached with `handle_for::<Concrete>()` and `get::<Concrete>()`. See [`ComponentPtr::new_dyn`].
+    ///
+    /// # Panics
+    /// Panics if the store is finalized, or if a component of the same concrete type already exists.
+    pub fn insert_dyn(&mut self, component: Box<dyn Any + Send + Sync>) {
+        // deref first, as the box itself is `Any` too
+        let id = (*component).type_id();
+        self.insert_ptr(id, ComponentPtr::new_dyn(component));
+    }
+
+    /// Adds `ptr` to the modification map under `id`, recording its insertion order.
+    ///
+    /// Components added with `insert_dynamic` count as duplicates too, as the finalized one would shadow them.
+    fn insert_ptr(&mut self, id: TypeId, ptr: ComponentPtr) -> ComponentPtr {
+        if self.is_ready() {
             panic!("Cannot insert component into finalized ComponentDB");
         }
 
-        let mut guard = self.modification_map.write();
-        if guard.contains_key(&TypeId::of::<T>()) {
+        let mut guard = self.inner.modification_map.write();
+        if guard.contains_key(&id) || self.inner.dynamic.read().contains_key(&id) {
+            panic!(
+                "Component of type {} already exists in State",
+                ptr.type_name()
+            );
+        }
+
+        guard.insert(id, ptr.clone());
+        self.inner.order.lock().push(id);
+        ptr
+    }
+
+    /// Inserts a component into the dynamic layer of the database.
+    ///
+    /// Unlike `insert`, this can be called after `finish_initialization` and only requires a shared reference, so
+    /// gameplay code can register components at runtime. Dynamic components are looked up after the finalized map.
+    ///
+    /// # Panics
+    /// Panics if a component of type T already exists in the database.
+    pub fn insert_dynamic<T: 'static + Send + Sync>(&self, component: T) -> ComponentHandle<T> {
+        let id = TypeId::of::<T>();
+        let exists_static = self
+            .inner
+            .map
+            .get()
+            .is_some_and(|map| map.contains_key(&id))
+            || self.inner.modification_map.read().contains_key(&id);
+
+        let mut dynamic = self.inner.dynamic.write();
+        if exists_static || dynamic.contains_key(&id) {
             panic!(
                 "Component of type {} already exists in State",
                 std::any::type_name::<T>()
@@ -68,10 +200,9 @@ impl ComponentStore {
         }
 
         let ptr = ComponentPtr::new(component);
-        guard.insert(TypeId::of::<T>(), ptr.clone());
-
-        drop(guard);
-        self.handle_for::<T>()
+        dynamic.insert(id, ptr.clone());
+        self.inner.order.lock().push(id);
+        ComponentHandle::new(ptr)
     }
 
     /// Creates a handle for a component of the specified type.
@@ -79,22 +210,25 @@ impl ComponentStore {
     /// NOTE: Handles for non-existent components can be created; attempting to use them without inserting the component first will panic.
     pub fn handle_for<T: 'static + Send + Sync>(&self) -> ComponentHandle<T> {
         // if the read only map is initialized, use it
-        if let Some(map) = self.map.get()
+        if let Some(map) = self.inner.map.get()
             && let Some(ptr) = map.get(&TypeId::of::<T>())
         {
             return ComponentHandle::new(ptr.clone());
         }
 
+        // then the components registered at runtime
+        if let Some(ptr) = self.inner.dynamic.read().get(&TypeId::of::<T>()) {
+            return ComponentHandle::new(ptr.clone());
+        }
+
         // otherwise, use the modification map
-        let guard = self.modification_map.read();
+        let guard = self.inner.modification_map.read();
         if let Some(ptr) = guard.get(&TypeId::of::<T>()) {
             return ComponentHandle::new(ptr.clone());
         }
         drop(guard);
 
-        // Are we init yet?
-        let is_init = self.map.get().is_some();
-        if is_init {
+        if self.is_ready() {
             panic!(
                 "Component of type {} does not exist in ComponentDB",
                 std::any::type_name::<T>()
@@ -103,9 +237,11 @@ impl ComponentStore {
 
         let ptr = ComponentPtr::uninitialized::<T>();
 
-        self.modification_map
+        self.inner
+            .modification_map
             .write()
             .insert(TypeId::of::<T>(), ptr.clone());
+        self.inner.order.lock().push(TypeId::of::<T>());
 
         ComponentHandle::new(ptr)
     }
@@ -115,17 +251,146 @@ impl ComponentStore {
     pub fn handle(&self) -> ComponentStoreHandle {
         self.clone()
     }
+
+    /// Looks up the pointer for a component by its TypeId, if it exists.
+    fn ptr_for(&self, id: TypeId) -> Option<ComponentPtr> {
+        if let Ok(map) = self.get_map_checked()
+            && let Some(ptr) = map.get(&id)
+        {
+            return Some(ptr.clone());
+        }
+
+        if let Some(ptr) = self.inner.dynamic.read().get(&id) {
+            return Some(ptr.clone());
+        }
+
+        self.inner.modification_map.read().get(&id).cloned()
+    }
+
+    /// Moves the component of type T from `from` into this store.
+    ///
+    /// The underlying component is moved rather than copied, so existing `ComponentHandle<T>`s keep working.
+    /// Finalized stores are modified in place, which requires them to be uniquely owned (not cloned).
+    ///
+    /// # Panics
+    /// Panics if `from` does not contain a component of type T, if this store already does, or if a finalized store
+    /// involved is shared.
+    #[track_caller]
+    pub fn migrate<T: 'static + Send + Sync>(&mut self, from: &mut ComponentStore) {
+        let id = TypeId::of::<T>();
+        if self.ptr_for(id).is_some() {
+            panic!(
+                "Component of type {} already exists in State",
+                std::any::type_name::<T>()
+            );
+        }
+
+        // check before taking the component, so a failure here does not lose it
+        let finalized = self.inner.map.get().is_some();
+        if finalized && Arc::get_mut(&mut self.inner).is_none() {
+            panic!("ComponentStore::migrate requires finalized stores to be uniquely owned");
+        }
+
+        let ptr = from.take_ptr(id).unwrap_or_else(|| {
+            panic!(
+                "Component {} not found in ComponentDB",
+                std::any::type_name::<T>()
+            )
+        });
+
+        self.inner.order.lock().push(id);
+        if finalized {
+            Arc::get_mut(&mut self.inner)
+                .and_then(|inner| inner.map.get_mut())
+                .expect("checked above")
+                .insert(id, ptr);
+        } else {
+            self.inner.modification_map.write().insert(id, ptr);
+        }
+    }
+
+    /// Removes the pointer for a component by its TypeId from every map holding it.
+    #[track_caller]
+    fn take_ptr(&mut self, id: TypeId) -> Option<ComponentPtr> {
+        let mut ptr = None;
+        if self
+            .inner
+            .map
+            .get()
+            .is_some_and(|map| map.contains_key(&id))
+        {
+            let map = Arc::get_mut(&mut self.inner)
+                .and_then(|inner| inner.map.get_mut())
+                .expect("ComponentStore::migrate requires finalized stores to be uniquely owned");
+            ptr = map.remove(&id);
+        }
+
+        self.inner.order.lock().retain(|&other| other != id);
+
+        // the modification map keeps its entries after finalization, so it is always cleared as well
+        let modified = self.inner.modification_map.write().remove(&id);
+        let dynamic = self.inner.dynamic.write().remove(&id);
+        ptr.or(dynamic).or(modified)
+    }
+
+    /// Calls `f` with the TypeId and type name of every component, in the order they were added to the store.
+    ///
+    /// Handles created by `handle_for` for components that were not inserted yet are included, at the position
+    /// of the first `handle_for` call.
+    pub fn for_each(&self, mut f: impl FnMut(TypeId, &'static str)) {
+        let order = self.inner.order.lock().clone();
+        for id in order {
+            if let Some(ptr) = self.ptr_for(id) {
+                f(id, ptr.type_name());
+            }
+        }
+    }
+
+    /// Registers a component type to be captured by `capture_history`.
+    ///
+    /// Only `Clone` components can participate in history.
+    pub fn track_history<T: Clone + Send + Sync + 'static>(&self) {
+        self.inner.history.lock().track::<T>();
+    }
+
+    /// Captures a snapshot of all tracked components into the history ring buffer.
+    ///
+    /// This is intended to be called once per frame. At most `max_frames` snapshots are kept; older ones are discarded.
+    pub fn capture_history(&self, max_frames: usize) {
+        self.inner
+            .history
+            .lock()
+            .capture(max_frames, |id| self.ptr_for(id));
+    }
+
+    /// Re-applies the snapshot captured `n` frames ago into the live components, taking write locks on each.
+    /// `0` is the most recent capture.
+    ///
+    /// Returns false if there is no such frame.
+    #[track_caller]
+    pub fn restore_frame(&self, n: usize) -> bool {
+        self.inner.history.lock().restore(n, |id| self.ptr_for(id))
+    }
+
+    /// Returns the number of frames currently held in the history.
+    pub fn history_len(&self) -> usize {
+        self.inner.history.lock().len()
+    }
 }
 
 impl Debug for ComponentStore {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(map) = self.map.get() {
+        if let Some(map) = self.inner.map.get() {
             f.debug_struct("ComponentDB")
                 .field("finalized", &true)
                 .field("components", &map.values().collect::<Vec<_>>())
+                .field(
+                    "dynamic",
+                    &self.inner.dynamic.read().values().collect::<Vec<_>>(),
+                )
                 .finish()
         } else {
-            let guard = self.modification_map.read();
+            let guard = self.inner.modification_map.read();
             f.debug_struct("ComponentDB")
                 .field("finalized", &false)
                 .field("components", &guard.values().collect::<Vec<_>>())
@@ -143,18 +408,8 @@ impl Default for ComponentStore {
 impl ComponentStore {
     /// Gets a reference to a component of the specified type.
     pub fn get_checked<T: 'static>(&self) -> Option<ComponentReadGuard<T>> {
-        // if the read only map is initialized, use it
-        if let Some(map) = self.map.get()
-            && let Some(ptr) = map.get(&TypeId::of::<T>())
-        {
-            return Some(ptr.read());
-        }
-
-        if let Some(guard) = self.modification_map.read().get(&TypeId::of::<T>()) {
-            return Some(guard.read());
-        }
-
-        None
+        let ptr = self.ptr_for(TypeId::of::<T>())?;
+        Some(ptr.read())
     }
 
     /// Gets a reference to a component of the specified type.
@@ -169,20 +424,30 @@ impl ComponentStore {
         }
     }
 
+    /// Read locks every component in `types` until the returned barrier is dropped.
+    ///
+    /// This gives a consistent view of several components at once: writers to any of them block until the barrier
+    /// is dropped. Locks are acquired in a fixed order, so overlapping barriers on different threads can't deadlock.
+    ///
+    /// # Panics
+    /// Panics if any type is not in the store, or if any component is poisoned.
+    #[track_caller]
+    pub fn read_barrier(&self, types: &[TypeId]) -> ReadBarrier {
+        let ptrs = types
+            .iter()
+            .map(|id| {
+                self.ptr_for(*id)
+                    .unwrap_or_else(|| panic!("Component {:?} not found in ComponentDB", id))
+            })
+            .collect();
+        ReadBarrier::lock(ptrs)
+    }
+
     /// Gets a mutable reference to a component of the specified type.
+    #[track_caller]
     pub fn get_mut_checked<T: 'static>(&self) -> Option<ComponentWriteGuard<T>> {
-        // if the read only map is initialized, use it
-        if let Some(map) = self.map.get()
-            && let Some(ptr) = map.get(&TypeId::of::<T>())
-        {
-            return Some(ptr.write());
-        }
-
-        if let Some(guard) = self.modification_map.read().get(&TypeId::of::<T>()) {
-            return Some(guard.write());
-        }
-
-        None
+        let ptr = self.ptr_for(TypeId::of::<T>())?;
+        Some(ptr.write())
     }
 
     /// Gets a mutable reference to a component of the specified type.
@@ -197,3 +462,342 @@ impl ComponentStore {
         }
     }
 }
+
+/// A view of the finalized components of a `ComponentStore`, created by `ComponentStore::bundle`.
+///
+/// Lookups go straight to the finalized map, skipping the checks `ComponentStore` makes for unfinalized and dynamic
+/// components.
+#[derive(Clone, Copy)]
+pub struct ResolvedStore<'a> {
+    map: &'a ResourceMap,
+}
+
+impl ResolvedStore<'_> {
+    /// Gets a reference to a component of the specified type, or None if it is not a finalized component.
+    #[track_caller]
+    pub fn get_checked<T: 'static>(&self) -> Option<ComponentReadGuard<T>> {
+        Some(self.map.get(&TypeId::of::<T>())?.read())
+    }
+
+    /// Gets a reference to a component of the specified type.
+    #[track_caller]
+    pub fn get<T: 'static>(&self) -> ComponentReadGuard<T> {
+        self.get_checked::<T>().unwrap_or_else(|| {
+            panic!(
+                "Component {} not found in ResolvedStore",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    /// Gets a mutable reference to a component of the specified type, or None if it is not a finalized component.
+    #[track_caller]
+    pub fn get_mut_checked<T: 'static>(&self) -> Option<ComponentWriteGuard<T>> {
+        Some(self.map.get(&TypeId::of::<T>())?.write())
+    }
+
+    /// Gets a mutable reference to a component of the specified type.
+    #[track_caller]
+    pub fn get_mut<T: 'static>(&self) -> ComponentWriteGuard<T> {
+        self.get_mut_checked::<T>().unwrap_or_else(|| {
+            panic!(
+                "Component {} not found in ResolvedStore",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    /// Returns a handle to a component of the specified type, or None if it is not a finalized component.
+    pub fn handle_for<T: 'static + Send + Sync>(&self) -> Option<ComponentHandle<T>> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .map(|ptr| ComponentHandle::new(ptr.clone()))
+    }
+}
+
+impl Debug for ResolvedStore<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResolvedStore")
+            .field("components", &self.map.values().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComponentHandle, ComponentStore, NotInitialized};
+
+    #[test]
+    fn test_bundle() {
+        let mut store = ComponentStore::new();
+        store.insert(5u32);
+        store.insert(String::from("kiwi"));
+        assert_eq!(store.bundle().unwrap_err(), NotInitialized);
+
+        store.finish_initialization();
+        store.insert_dynamic(1.5f32);
+
+        let bundle = store.bundle().unwrap();
+        assert_eq!(*bundle.get::<u32>(), 5);
+        *bundle.get_mut::<String>() += "!";
+        assert_eq!(*store.get::<String>(), "kiwi!");
+        assert!(bundle.handle_for::<u32>().is_some());
+        // dynamic components aren't part of the finalized map
+        assert!(bundle.get_checked::<f32>().is_none());
+    }
+
+    #[test]
+    fn test_clones_share_one_pointer() {
+        assert_eq!(
+            std::mem::size_of::<ComponentStore>(),
+            std::mem::size_of::<usize>()
+        );
+
+        let mut store = ComponentStore::new();
+        let clone = store.clone();
+        store.insert(5u32);
+        store.finish_initialization();
+        assert_eq!(*clone.get::<u32>(), 5);
+
+        clone.insert_dynamic(String::from("shared"));
+        assert_eq!(*store.get::<String>(), "shared");
+    }
+
+    #[test]
+    fn test_insert_uninitialized_cycle() {
+        struct Parent {
+            child: ComponentHandle<Child>,
+        }
+        struct Child {
+            parent: ComponentHandle<Parent>,
+            value: u32,
+        }
+
+        let mut store = ComponentStore::new();
+        let parent = store.insert_uninitialized::<Parent>();
+        let child = store.insert(Child {
+            parent: parent.clone(),
+            value: 7,
+        });
+        assert!(!parent.is_initialized());
+
+        assert!(parent.initialize(Parent {
+            child: child.clone()
+        }));
+        // a slot can only be initialized once
+        assert!(!parent.initialize(Parent { child }));
+        store.finish_initialization();
+
+        let child = store.get::<Child>();
+        assert_eq!(child.parent.read().child.read().value, 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "Component not initialized")]
+    fn test_read_before_initialize_panics() {
+        let mut store = ComponentStore::new();
+        let handle = store.insert_uninitialized::<u32>();
+        let _ = handle.read();
+    }
+
+    #[test]
+    fn test_ready_after_finalization() {
+        let mut store = ComponentStore::new();
+        store.insert(1u32);
+        assert!(!store.is_ready());
+        assert_eq!(store.check_ready(), Err(NotInitialized));
+
+        store.finish_initialization();
+        assert!(store.is_ready());
+        assert!(store.check_ready().is_ok());
+        assert_eq!(store.get_map_checked().map(|map| map.len()), Ok(1));
+    }
+
+    #[test]
+    fn test_insert_dynamic_after_finalization() {
+        let mut store = ComponentStore::new();
+        store.insert(1u32);
+        store.finish_initialization();
+
+        let handle = store.insert_dynamic(String::from("late"));
+        assert_eq!(*store.get::<String>(), "late");
+        assert_eq!(*store.handle_for::<String>().read(), "late");
+
+        handle.write().push('r');
+        assert_eq!(*store.get::<String>(), "later");
+        assert_eq!(*store.get::<u32>(), 1);
+    }
+
+    #[test]
+    fn test_for_each_insertion_order() {
+        let mut store = ComponentStore::new();
+        store.insert(1u32);
+        store.insert(String::new());
+        store.insert(1.0f64);
+        store.insert(1i8);
+        store.finish_initialization();
+        store.insert_dynamic(1u64);
+
+        let mut names = Vec::new();
+        store.for_each(|_, name| names.push(name));
+        assert_eq!(
+            names,
+            vec!["u32", "alloc::string::String", "f64", "i8", "u64"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_populates_map() {
+        let mut store = ComponentStore::new();
+        store.insert(5u32);
+
+        // resolving doesn't finalize the store early
+        assert_eq!(store.resolve(), Err(NotInitialized));
+        assert!(store.inner.map.get().is_none());
+        store.insert(String::from("late"));
+
+        store.finish_initialization();
+        assert_eq!(store.clone().resolve(), Ok(()));
+        assert!(store.inner.map.get().is_some_and(|map| map.len() == 2));
+        assert_eq!(*store.get::<u32>(), 5);
+    }
+
+    #[test]
+    fn test_migrate_preserves_handles() {
+        let mut from = ComponentStore::new();
+        let handle = from.insert(String::from("carried"));
+        from.insert(1u32);
+        from.finish_initialization();
+
+        let mut to = ComponentStore::new();
+        to.insert(2u32);
+        to.finish_initialization();
+
+        to.migrate::<String>(&mut from);
+        assert!(from.get_checked::<String>().is_none());
+        assert_eq!(*to.get::<String>(), "carried");
+
+        handle.write().push('!');
+        assert_eq!(*to.get::<String>(), "carried!");
+        assert_eq!(*to.handle_for::<String>().read(), "carried!");
+    }
+
+    #[test]
+    fn test_insert_dyn() {
+        use std::any::Any;
+
+        trait Greeter: Any + Send + Sync {
+            fn greet(&self) -> String;
+        }
+
+        struct English(&'static str);
+        impl Greeter for English {
+            fn greet(&self) -> String {
+                format!("hello, {}", self.0)
+            }
+        }
+
+        let mut store = ComponentStore::new();
+        let greeter: Box<dyn Greeter + Send + Sync> = Box::new(English("kiwi"));
+        store.insert_dyn(greeter);
+        let handle = store.handle_for::<English>();
+        store.finish_initialization();
+
+        assert_eq!(store.get::<English>().greet(), "hello, kiwi");
+        handle.write().0 = "world";
+        assert_eq!(handle.read().greet(), "hello, world");
+    }
+
+    #[test]
+    #[should_panic(expected = "already exists")]
+    fn test_insert_after_insert_dynamic_duplicate() {
+        let mut store = ComponentStore::new();
+        store.insert_dynamic(1u32);
+        store.insert(2u32);
+    }
+
+    #[test]
+    #[should_panic(expected = "already exists")]
+    fn test_insert_dynamic_duplicate() {
+        let mut store = ComponentStore::new();
+        store.insert(1u32);
+        store.finish_initialization();
+        store.insert_dynamic(2u32);
+    }
+
+    #[test]
+    fn test_store_and_handle_share_guard_types() {
+        use super::{ComponentReadGuard, ComponentWriteGuard};
+
+        fn read_value(guard: ComponentReadGuard<u32>) -> u32 {
+            *guard
+        }
+
+        fn bump(mut guard: ComponentWriteGuard<u32>) {
+            *guard += 1;
+        }
+
+        let mut store = ComponentStore::new();
+        let handle = store.insert(1u32);
+        store.finish_initialization();
+
+        assert_eq!(read_value(store.get::<u32>()), 1);
+        assert_eq!(read_value(handle.read()), 1);
+
+        bump(store.get_mut::<u32>());
+        bump(handle.write());
+        assert_eq!(read_value(handle.read()), 3);
+        assert_eq!(read_value(store.get::<u32>()), 3);
+    }
+
+    #[test]
+    fn test_read_barrier_blocks_writers() {
+        use std::{
+            any::TypeId,
+            sync::{
+                Arc,
+                atomic::{AtomicBool, Ordering},
+            },
+            thread,
+            time::Duration,
+        };
+
+        let mut store = ComponentStore::new();
+        let number = store.insert(1u32);
+        let text = store.insert(String::from("a"));
+        store.finish_initialization();
+
+        let barrier = store.read_barrier(&[TypeId::of::<u32>(), TypeId::of::<String>()]);
+        assert_eq!(barrier.len(), 2);
+        assert_eq!(barrier.get::<u32>(), Some(&1));
+        assert_eq!(barrier.get::<String>().map(String::as_str), Some("a"));
+
+        let written = Arc::new(AtomicBool::new(false));
+        let writers = [
+            thread::spawn({
+                let written = written.clone();
+                move || {
+                    *number.write() += 1;
+                    written.store(true, Ordering::SeqCst);
+                }
+            }),
+            thread::spawn({
+                let written = written.clone();
+                move || {
+                    text.write().push('b');
+                    written.store(true, Ordering::SeqCst);
+                }
+            }),
+        ];
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!written.load(Ordering::SeqCst));
+
+        drop(barrier);
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(*store.get::<u32>(), 2);
+        assert_eq!(*store.get::<String>(), "ab");
+    }
+}
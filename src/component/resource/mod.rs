@@ -2,23 +2,20 @@ use std::{
     alloc::Layout,
     any::Any,
     fmt,
-    mem::MaybeUninit,
     panic::Location,
     ptr::NonNull,
-    sync::atomic::{
-        AtomicBool, AtomicIsize, AtomicPtr, AtomicU8, AtomicU64, AtomicUsize, Ordering,
-    },
-    thread,
+    sync::atomic::{AtomicIsize, AtomicU8, AtomicUsize, Ordering},
 };
 
 use bitflags::bitflags;
-use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 pub mod read;
 mod weak;
 pub mod write;
+mod writer;
 
 pub use weak::WeakComponentPtr;
+use writer::WriterSlot;
 
 use crate::{component::resource::read::ComponentReadGuard, prelude::ComponentWriteGuard};
 
@@ -53,7 +50,7 @@ impl ComponentPtr {
                 weak: AtomicUsize::new(1),
                 state: AtomicIsize::new(0),
                 flags: AtomicU8::new(LockState::IS_INIT.bits()),
-                writer: (AtomicU64::new(0), AtomicPtr::new(std::ptr::null_mut())),
+                writer: WriterSlot::new(),
                 component: Some(NonNull::new_unchecked(component_trait_ptr)),
                 layout: (layout, offset),
                 type_name: std::any::type_name::<T>(),
@@ -65,6 +62,45 @@ impl ComponentPtr {
         }
     }
 
+    /// Creates a new ComponentPtr wrapping an already boxed component, such as a `Box<dyn MyTrait>` upcast to
+    /// `Box<dyn Any + Send + Sync>`.
+    ///
+    /// The box is kept as a separate allocation rather than being moved inline. Reads and writes still downcast to the
+    /// concrete type. Use `ComponentStore::insert_dyn` to add one to a store.
+    ///
+    /// A trait object doesn't carry the name of its concrete type, so `type_name` reports the erased type.
+    pub fn new_dyn(component: Box<dyn Any + Send + Sync>) -> Self {
+        let type_name = std::any::type_name_of_val(&*component);
+
+        // only the header is allocated, so there is nothing past it
+        let layout = Layout::new::<ComponentInner>().pad_to_align();
+
+        let raw_ptr = unsafe { std::alloc::alloc(layout) };
+        if raw_ptr.is_null() {
+            std::alloc::handle_alloc_error(layout);
+        }
+
+        let inner_ptr = raw_ptr as *mut ComponentInner;
+        let component_trait_ptr = Box::into_raw(component);
+
+        unsafe {
+            inner_ptr.write(ComponentInner {
+                strong: AtomicUsize::new(1),
+                weak: AtomicUsize::new(1),
+                state: AtomicIsize::new(0),
+                flags: AtomicU8::new((LockState::IS_INIT | LockState::BOXED).bits()),
+                writer: WriterSlot::new(),
+                component: Some(NonNull::new_unchecked(component_trait_ptr)),
+                layout: (layout, layout.size()),
+                type_name,
+            })
+        };
+
+        Self {
+            data: unsafe { NonNull::new_unchecked(inner_ptr) },
+        }
+    }
+
     /// Creates a new uninitialized ComponentPtr for the given type T.
     /// The caller is responsible for initializing the component before use.
     pub(crate) fn uninitialized<T: Send + Sync + 'static>() -> Self {
@@ -83,7 +119,7 @@ impl ComponentPtr {
                 weak: AtomicUsize::new(1),
                 state: AtomicIsize::new(0),
                 flags: AtomicU8::new(0),
-                writer: (AtomicU64::new(0), AtomicPtr::new(std::ptr::null_mut())),
+                writer: WriterSlot::new(),
                 component: None,
                 layout: (layout, offset),
                 type_name: std::any::type_name::<T>(),
@@ -105,6 +141,18 @@ impl ComponentPtr {
         unsafe { self.data.as_mut() }
     }
 
+    /// Returns the address of the shared allocation, used to order lock acquisition.
+    pub(crate) fn addr(&self) -> usize {
+        self.data.as_ptr() as usize
+    }
+
+    /// Returns the type name of the component, as given by `std::any::type_name`.
+    ///
+    /// This is available even before the component is initialized.
+    pub fn type_name(&self) -> &'static str {
+        self.get_ref().type_name
+    }
+
     /// Checks if the component has been orphaned (i.e., removed from its parent store).
     pub fn is_orphaned(&self) -> bool {
         let inner = unsafe { self.data.as_ref() };
@@ -127,7 +175,13 @@ impl ComponentPtr {
             // Drop the component
             let component_ref = component_ptr.as_ptr();
             inner.component = None;
-            unsafe { std::ptr::drop_in_place(component_ref) };
+            let flags = LockState::from_bits_truncate(inner.flags.load(Ordering::Relaxed));
+            if flags.contains(LockState::BOXED) {
+                // created by `new_dyn`, so the component owns its own allocation
+                drop(unsafe { Box::from_raw(component_ref) });
+            } else {
+                unsafe { std::ptr::drop_in_place(component_ref) };
+            }
         }
     }
 
@@ -166,19 +220,19 @@ impl ComponentPtr {
     /// Attempts to get a read guard for the component of type T.
     #[track_caller]
     pub fn try_read<T: 'static>(&self) -> Result<Option<ComponentReadGuard<T>>, TypeMismatchError> {
-        let inner = unsafe { self.data.as_ref() };
-        if inner.component.is_none() {
+        if !self.is_initialized() {
             return Ok(None);
         }
+        let inner = unsafe { self.data.as_ref() };
 
         if unsafe { inner.component.unwrap().as_ref() }.is::<T>() {
             // SAFETY: We just checked that the type matches.
             unsafe { Ok(Some(ComponentReadGuard::lock(self.clone()))) }
         } else {
-            Err(TypeMismatchError::new(
-                std::any::type_name::<T>(),
-                inner.type_name,
-            ))
+            Err(
+                TypeMismatchError::new(std::any::type_name::<T>(), inner.type_name)
+                    .with_ptr(self.clone()),
+            )
         }
     }
 
@@ -190,15 +244,61 @@ impl ComponentPtr {
             .expect("ComponentPtr::read: Component not initialized")
     }
 
+    /// Gets a read guard for the component of type T, even if the component is poisoned.
+    ///
+    /// **Warning:** a poisoned component was mid-write when a panic occurred, so its value may be inconsistent
+    /// or violate its own invariants. This is intended for diagnostics (such as crash reporting) only.
+    ///
+    /// # Panics
+    /// Panics on type mismatch or if the component is not initialized.
+    #[track_caller]
+    pub fn read_poisoned<T: 'static>(&self) -> ComponentReadGuard<T> {
+        assert!(
+            self.is_initialized(),
+            "ComponentPtr::read_poisoned: Component not initialized"
+        );
+        assert!(
+            self.is::<T>(),
+            "ComponentPtr::read_poisoned: Type mismatch when getting component"
+        );
+        // SAFETY: We just checked that the type matches.
+        unsafe { ComponentReadGuard::lock_ignore_poison(self.clone()) }
+    }
+
+    /// Checks if the component is poisoned, i.e. a write guard was dropped during a panic.
+    ///
+    /// Poisoned components can only be read through `read_poisoned`.
+    pub fn is_poisoned(&self) -> bool {
+        let flags = LockState::from_bits_truncate(self.get_ref().flags.load(Ordering::Acquire));
+        flags.contains(LockState::POISONED)
+    }
+
+    /// Clears the poisoned state, so the component can be locked normally again.
+    ///
+    /// Like `std::sync::Mutex::clear_poison`, this is for callers that have checked or repaired the value, e.g.
+    /// through `read_poisoned`.
+    pub fn clear_poison(&self) {
+        self.get_ref()
+            .flags
+            .fetch_and(!LockState::POISONED.bits(), Ordering::Release);
+    }
+
+    /// Marks the component as poisoned.
+    pub(crate) fn poison(&self) {
+        self.get_ref()
+            .flags
+            .fetch_or(LockState::POISONED.bits(), Ordering::Release);
+    }
+
     /// Attempts to get a write guard for the component of type T.
     #[track_caller]
     pub fn try_write<T: 'static>(
         &self,
     ) -> Result<Option<ComponentWriteGuard<T>>, TypeMismatchError> {
-        let inner = unsafe { self.data.as_ref() };
-        if inner.component.is_none() {
+        if !self.is_initialized() {
             return Ok(None);
         }
+        let inner = unsafe { self.data.as_ref() };
 
         if unsafe { inner.component.unwrap().as_ref() }.is::<T>() {
             // SAFETY: We just checked that the type matches.
@@ -209,10 +309,10 @@ impl ComponentPtr {
                 )))
             }
         } else {
-            Err(TypeMismatchError::new(
-                std::any::type_name::<T>(),
-                inner.type_name,
-            ))
+            Err(
+                TypeMismatchError::new(std::any::type_name::<T>(), inner.type_name)
+                    .with_ptr(self.clone()),
+            )
         }
     }
 
@@ -224,6 +324,96 @@ impl ComponentPtr {
             .expect("ComponentPtr::write: Component not initialized")
     }
 
+    /// Read locks the component, runs `f` with it, and releases the lock before returning.
+    ///
+    /// Prefer this over holding a guard for short accesses, since the lock can't outlive the closure.
+    ///
+    /// # Panics
+    /// Panics on type mismatch or if the component is not initialized.
+    #[track_caller]
+    pub fn with_read<T: 'static, R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.read::<T>())
+    }
+
+    /// Write locks the component, runs `f` with it, and releases the lock before returning.
+    ///
+    /// # Panics
+    /// Panics on type mismatch or if the component is not initialized.
+    #[track_caller]
+    pub fn with_write<T: 'static, R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.write::<T>())
+    }
+
+    /// Replaces the component with `new` while holding the write lock, returning the old value.
+    ///
+    /// Unlike `initialize`, this works on components that are already initialized, and existing handles see the
+    /// new value.
+    ///
+    /// # Panics
+    /// Panics if the component is not initialized.
+    #[track_caller]
+    pub fn replace<T: 'static>(&self, new: T) -> Result<T, TypeMismatchError> {
+        let mut guard = self
+            .try_write::<T>()?
+            .expect("ComponentPtr::replace: Component not initialized");
+        Ok(std::mem::replace(&mut *guard, new))
+    }
+
+    /// Attempts to get a read guard for the component of type T without blocking.
+    ///
+    /// If a writer holds the lock, the error includes where it was acquired.
+    #[track_caller]
+    pub fn try_read_now<T: 'static>(&self) -> Result<ComponentReadGuard<T>, TryLockError> {
+        self.check_lockable::<T>()?;
+        // SAFETY: check_lockable verified the type.
+        unsafe { ComponentReadGuard::try_lock(self.clone()) }
+            .map_err(|held_at| TryLockError::WouldBlock { held_at })
+    }
+
+    /// Attempts to get a write guard for the component of type T without blocking.
+    ///
+    /// If another writer holds the lock, the error includes where it was acquired. If readers hold the lock,
+    /// `held_at` is None.
+    #[track_caller]
+    pub fn try_write_now<T: 'static>(&self) -> Result<ComponentWriteGuard<T>, TryLockError> {
+        self.check_lockable::<T>()?;
+        // SAFETY: check_lockable verified the type.
+        unsafe { ComponentWriteGuard::try_lock(self.clone(), Location::caller()) }
+            .map_err(|held_at| TryLockError::WouldBlock { held_at })
+    }
+
+    fn check_lockable<T: 'static>(&self) -> Result<(), TryLockError> {
+        if !self.is_initialized() {
+            return Err(TryLockError::Uninitialized);
+        }
+        if self.is_poisoned() {
+            return Err(TryLockError::Poisoned);
+        }
+        if !self.is::<T>() {
+            return Err(
+                TypeMismatchError::new(std::any::type_name::<T>(), self.type_name())
+                    .with_ptr(self.clone())
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns true if the component is currently write locked.
+    ///
+    /// This is a relaxed snapshot: the lock may be taken or released right after it is read, so it should only be
+    /// used as a heuristic (e.g. to schedule work away from busy components), never for correctness.
+    pub fn is_write_locked(&self) -> bool {
+        self.get_ref().state.load(Ordering::Relaxed) == -1
+    }
+
+    /// Returns the number of read guards currently held on the component.
+    ///
+    /// Like `is_write_locked`, this is a relaxed snapshot that may be stale as soon as it is returned.
+    pub fn reader_count(&self) -> usize {
+        self.get_ref().state.load(Ordering::Relaxed).max(0) as usize
+    }
+
     /// Checks if the component is of type T.
     pub fn is<T: 'static>(&self) -> bool {
         let inner = unsafe { self.data.as_ref() };
@@ -237,58 +427,57 @@ impl ComponentPtr {
     }
 
     /// Initializes the component with the given value.
-    pub fn initialize<T: Send + Sync + 'static>(&mut self, component: T) -> Option<()> {
-        let inner = unsafe { self.data.as_mut() };
-
-        inner
+    ///
+    /// Returns None if the component was already initialized (or is being initialized by another thread).
+    pub(crate) fn initialize<T: Send + Sync + 'static>(&mut self, component: T) -> Option<()> {
+        let inner_ptr = self.data.as_ptr();
+        let offset = self.get_ref().layout.1;
+
+        // claim the slot first so that only one initializer can write the component
+        self.get_ref()
             .flags
-            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |flags| {
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |flags| {
                 let mut state = LockState::from_bits_truncate(flags);
-                if state.contains(LockState::IS_INIT) {
+                if state.intersects(LockState::IS_INIT | LockState::INITIALIZING) {
                     None
                 } else {
-                    state.insert(LockState::IS_INIT);
+                    state.insert(LockState::INITIALIZING);
                     Some(state.bits())
                 }
             })
             .ok()?;
 
-        let component_ptr =
-            unsafe { self.data.cast::<u8>().add(inner.layout.1).as_ptr() as *mut T };
+        let component_ptr = unsafe { self.data.cast::<u8>().add(offset).as_ptr() as *mut T };
         unsafe {
             component_ptr.write(component);
         }
 
         let component_trait_ptr: *mut (dyn Any + Send + Sync) = component_ptr;
-        inner.component = Some(unsafe { NonNull::new_unchecked(component_trait_ptr) });
-        Some(())
-    }
-
-    // Manually decrement the strong/weak counts, dropping the component if strong reaches zero.
-    pub unsafe fn release(&self) {
-        let inner = unsafe { self.data.as_ref() };
-        if inner.strong.fetch_sub(1, Ordering::Release) == 1 {
-            std::sync::atomic::fence(Ordering::Acquire);
-            unsafe {
-                let mut self_mut = self.clone();
-                self_mut.drop_component();
-            }
-            if inner.weak.fetch_sub(1, Ordering::Release) == 1 {
-                std::sync::atomic::fence(Ordering::Acquire);
-                unsafe {
-                    std::alloc::dealloc(self.data.as_ptr() as *mut u8, inner.layout.0);
-                }
-            }
+        // SAFETY: readers only access `component` after observing IS_INIT, which is published below.
+        unsafe {
+            (*inner_ptr).component = Some(NonNull::new_unchecked(component_trait_ptr));
         }
+
+        // publish the component. the Release here pairs with the Acquire in `is_initialized`,
+        // so any thread that sees IS_INIT also sees the component write above.
+        self.get_ref()
+            .flags
+            .fetch_update(Ordering::Release, Ordering::Relaxed, |flags| {
+                let mut state = LockState::from_bits_truncate(flags);
+                state.remove(LockState::INITIALIZING);
+                state.insert(LockState::IS_INIT);
+                Some(state.bits())
+            })
+            .ok()?;
+        Some(())
     }
 
-    /// Manually increment the strong/weak counts to retain the component.
-    pub unsafe fn retain(&self) {
-        let inner = unsafe { self.data.as_ref() };
-        // keep in mind we use both strong and weak counts to keep the inner alive
-        // weak is defined as keeping the enclosing structure alive, strong keeps the component alive
-        inner.strong.fetch_add(1, Ordering::Relaxed);
-        inner.weak.fetch_add(1, Ordering::Relaxed);
+    /// Checks if the component has been initialized.
+    ///
+    /// If this returns true, the component is guaranteed to be visible to the current thread.
+    pub fn is_initialized(&self) -> bool {
+        let flags = LockState::from_bits_truncate(self.get_ref().flags.load(Ordering::Acquire));
+        flags.contains(LockState::IS_INIT)
     }
 }
 
@@ -351,8 +540,8 @@ struct ComponentInner {
     // 0: no readers, a writer can be acquired
     // >0: number of active readers
     state: AtomicIsize,
-    // (tid, location) of the writer. location is only safe to read if tid == current_tid
-    writer: (AtomicU64, AtomicPtr<Location<'static>>),
+    // the thread holding the write lock and where it acquired it
+    writer: WriterSlot,
     flags: AtomicU8, // LockState
     // the actual component
     // this might seem strange, but whenever ComponentInner is allocated, the component is allocated inline after it.
@@ -375,17 +564,38 @@ struct ComponentInner {
 unsafe impl Send for ComponentPtr {}
 unsafe impl Sync for ComponentPtr {}
 
+/// Returns a unique, non-zero id for the current thread. Zero is reserved for "no writer".
+#[cfg(feature = "nightly")]
+pub(crate) fn thread_id() -> u64 {
+    std::thread::current().id().as_u64().get()
+}
+
+/// Returns a unique, non-zero id for the current thread. Zero is reserved for "no writer".
+///
+/// `ThreadId::as_u64` is unstable, so on stable each thread is assigned an id from a global counter the first time
+/// it asks for one.
+#[cfg(not(feature = "nightly"))]
+pub(crate) fn thread_id() -> u64 {
+    use std::sync::atomic::AtomicU64;
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static ID: u64 = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    }
+    ID.with(|id| *id)
+}
+
 #[track_caller]
 fn check_deadlock(state: &ComponentInner, lock_type: &str) {
-    let tid = state.writer.0.load(Ordering::Relaxed);
-    let this = thread::current().id().as_u64().get();
+    let tid = state.writer.owner();
+    let this = thread_id();
 
     // while `tid` could be modified between the load and the comparison,
     // the only time this check matters is if they are equal.
     // if they are equal, we know for sure we are deadlocked.
     if tid == this {
-        let location = state.writer.1.load(Ordering::Relaxed);
-        let location = unsafe { location.as_ref() };
+        // we are the writer, so the location can't change under us
+        let location = state.writer.location();
         panic!(
             "Deadlock detected: thread attempted to acquire {} lock while holding write lock: {:?}",
             lock_type, location
@@ -399,6 +609,12 @@ bitflags! {
         const ORPHANED = 1 << 0;
         /// a handle for a non existent component is waiting for initialization
         const IS_INIT = 1 << 1;
+        /// the component is currently being written by `initialize`
+        const INITIALIZING = 1 << 2;
+        /// a write guard was dropped while panicking, so the component may be inconsistent
+        const POISONED = 1 << 3;
+        /// the component lives in its own boxed allocation instead of inline (see `ComponentPtr::new_dyn`)
+        const BOXED = 1 << 4;
     }
 }
 
@@ -407,16 +623,68 @@ bitflags! {
 pub struct TypeMismatchError {
     expected: &'static str,
     found: &'static str,
+    // the component that was accessed, so the caller can retry as another type
+    ptr: Option<ComponentPtr>,
 }
 
 impl TypeMismatchError {
     pub fn new(expected: &'static str, found: &'static str) -> Self {
-        Self { expected, found }
+        Self {
+            expected,
+            found,
+            ptr: None,
+        }
+    }
+
+    /// Attaches the component that was accessed, so it can be recovered with `into_ptr`.
+    pub fn with_ptr(mut self, ptr: ComponentPtr) -> Self {
+        self.ptr = Some(ptr);
+        self
     }
+
+    /// Returns the component that was accessed, if it was attached.
+    pub fn ptr(&self) -> Option<&ComponentPtr> {
+        self.ptr.as_ref()
+    }
+
+    /// Takes the component that was accessed, if it was attached.
+    ///
+    /// `try_read` and `try_write` always attach it, so a dispatch table can fall through to the next type.
+    pub fn into_ptr(self) -> Option<ComponentPtr> {
+        self.ptr
+    }
+
+    /// Returns the type name that was requested.
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+
+    /// Returns the type name of the component that was actually stored.
+    pub fn found(&self) -> &'static str {
+        self.found
+    }
+}
+
+/// Returned by `ComponentPtr::try_read_now` and `try_write_now` when the lock can't be acquired immediately.
+#[derive(Debug, thiserror::Error)]
+pub enum TryLockError {
+    #[error(transparent)]
+    TypeMismatch(#[from] TypeMismatchError),
+    #[error("Component is not initialized")]
+    Uninitialized,
+    #[error("Component is poisoned")]
+    Poisoned,
+    /// The lock is held. `held_at` is where the current writer acquired it, or None if it is held by readers.
+    #[error("Component is locked (held at {held_at:?})")]
+    WouldBlock {
+        held_at: Option<&'static Location<'static>>,
+    },
 }
 
 #[cfg(test)]
 mod tests {
+    use std::thread;
+
     use rand::Rng;
 
     use crate::component;
@@ -490,11 +758,121 @@ mod tests {
     fn test_deadlock_check_nodeadlock() {
         let component = ComponentPtr::new(42u32);
         let inner = component.get_ref();
-        inner.writer.0.store(12345, Ordering::Relaxed);
+        inner.writer.publish(12345, Location::caller());
         // should not panic
         check_deadlock(inner, "test");
     }
 
+    #[test]
+    fn test_with_read_write_release_locks() {
+        let component = ComponentPtr::new(vec![1u32, 2]);
+
+        component.with_write(|v: &mut Vec<u32>| v.push(3));
+        let sum = component.with_read(|v: &Vec<u32>| v.iter().sum::<u32>());
+        assert_eq!(sum, 6);
+
+        // both locks were released, so a write can be taken right away
+        assert!(component.try_write_now::<Vec<u32>>().is_ok());
+        assert_eq!(component.get_ref().state.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_new_dyn() {
+        use std::sync::Arc;
+
+        trait Shape: Any + Send + Sync {
+            fn area(&self) -> f32;
+        }
+
+        struct Square(f32, #[allow(dead_code)] Arc<()>);
+        impl Shape for Square {
+            fn area(&self) -> f32 {
+                self.0 * self.0
+            }
+        }
+
+        let alive = Arc::new(());
+        let shape: Box<dyn Shape + Send + Sync> = Box::new(Square(2.0, alive.clone()));
+        let component = ComponentPtr::new_dyn(shape);
+
+        assert!(component.is::<Square>());
+        assert_eq!(component.read::<Square>().area(), 4.0);
+        component.write::<Square>().0 = 3.0;
+        assert_eq!(component.read::<Square>().area(), 9.0);
+        assert!(component.try_read::<u32>().is_err());
+
+        // dropping the last pointer drops the boxed component
+        assert_eq!(Arc::strong_count(&alive), 2);
+        drop(component);
+        assert_eq!(Arc::strong_count(&alive), 1);
+    }
+
+    #[test]
+    fn test_type_mismatch_returns_ptr() {
+        let component = ComponentPtr::new(3.5f32);
+
+        // try each type in turn, like a dispatch table would
+        let err = component.try_read::<u32>().unwrap_err();
+        assert_eq!(err.to_string(), "Type mismatch: expected u32, found f32");
+        let ptr = err.into_ptr().unwrap();
+        let err = ptr.try_write::<i64>().unwrap_err();
+        let ptr = err.into_ptr().unwrap();
+        assert_eq!(*ptr.try_read::<f32>().unwrap().unwrap(), 3.5);
+
+        assert!(TypeMismatchError::new("a", "b").ptr().is_none());
+
+        // the non-blocking paths attach it too
+        let Err(TryLockError::TypeMismatch(err)) = component.try_read_now::<u32>() else {
+            panic!("expected a type mismatch");
+        };
+        assert!(err.ptr().is_some());
+        let Err(TryLockError::TypeMismatch(err)) = component.try_write_now::<u32>() else {
+            panic!("expected a type mismatch");
+        };
+        assert!(err.into_ptr().unwrap().is::<f32>());
+    }
+
+    #[test]
+    fn test_lock_introspection() {
+        let component = ComponentPtr::new(1u32);
+        assert!(!component.is_write_locked());
+        assert_eq!(component.reader_count(), 0);
+
+        let (a, b) = (component.read::<u32>(), component.read::<u32>());
+        assert_eq!(component.reader_count(), 2);
+        drop((a, b));
+
+        let guard = component.write::<u32>();
+        assert!(component.is_write_locked());
+        assert_eq!(component.reader_count(), 0);
+        drop(guard);
+        assert!(!component.is_write_locked());
+    }
+
+    #[test]
+    fn test_replace_returns_old_value() {
+        let component = ComponentPtr::new(String::from("old"));
+        let other = component.clone();
+
+        assert_eq!(component.replace(String::from("new")).unwrap(), "old");
+        assert_eq!(*other.read::<String>(), "new");
+
+        let err = component.replace(5u32).unwrap_err();
+        assert_eq!(err.found(), std::any::type_name::<String>());
+        assert_eq!(*other.read::<String>(), "new");
+    }
+
+    #[test]
+    fn test_thread_id_is_unique_and_nonzero() {
+        let this = thread_id();
+        assert_ne!(this, 0);
+        assert_eq!(this, thread_id());
+
+        let other = thread::spawn(thread_id).join().unwrap();
+        assert_ne!(other, 0);
+        assert_ne!(this, other);
+    }
+
     #[test]
     #[should_panic(
         expected = "Deadlock detected: thread attempted to acquire abcd lock while holding write lock"
@@ -502,11 +880,47 @@ mod tests {
     fn test_deadlock_check_deadlock() {
         let component = ComponentPtr::new(42u32);
         let inner = component.get_ref();
-        let this = thread::current().id().as_u64().get();
-        inner.writer.0.store(this, Ordering::Relaxed);
+        let this = thread_id();
+        inner.writer.publish(this, Location::caller());
         check_deadlock(inner, "abcd");
     }
 
+    #[test]
+    fn test_deadlock_check_races_guard_drop() {
+        let ptr = ComponentPtr::new(0u32);
+
+        let writers = (0..4)
+            .map(|_| {
+                let ptr = ptr.clone();
+                thread::spawn(move || {
+                    for _ in 0..10000 {
+                        *ptr.write::<u32>() += 1;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let checker = {
+            let ptr = ptr.clone();
+            thread::spawn(move || {
+                // never the writer, so this must never panic or read a dangling location
+                for _ in 0..100000 {
+                    check_deadlock(ptr.get_ref(), "read");
+                }
+            })
+        };
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        checker.join().unwrap();
+
+        assert_eq!(*ptr.read::<u32>(), 40000);
+        let inner = ptr.get_ref();
+        assert_eq!(inner.writer.owner(), 0);
+        assert!(inner.writer.location().is_none());
+    }
+
     fn mixed_rw_heavy_multithread_read(component: ComponentPtr) {
         for i in 0..1000 {
             let guard = component.read::<u32>();
@@ -612,6 +1026,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_uninit_init_race() {
+        for i in 0..200u32 {
+            let ptr = ComponentPtr::uninitialized::<u32>();
+
+            let readers = (0..4)
+                .map(|_| {
+                    let ptr = ptr.clone();
+                    thread::spawn(move || {
+                        loop {
+                            if let Some(guard) = ptr.try_read::<u32>().unwrap() {
+                                assert_eq!(*guard, i);
+                                break;
+                            }
+                            std::hint::spin_loop();
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut writer = ptr.clone();
+            let init = thread::spawn(move || writer.initialize(i).is_some());
+
+            assert!(init.join().unwrap());
+            for reader in readers {
+                reader.join().unwrap();
+            }
+            assert!(ptr.clone().initialize(0u32).is_none());
+        }
+    }
+
+    fn poison(ptr: &ComponentPtr) {
+        let ptr = ptr.clone();
+        let result = thread::spawn(move || {
+            let mut guard = ptr.write::<u32>();
+            *guard = 13;
+            panic!("poisoning component");
+        })
+        .join();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_waiters_see_poison_from_panicking_writer() {
+        use std::{sync::mpsc, time::Duration};
+
+        for write in [false, true] {
+            let ptr = ComponentPtr::new(42u32);
+            let (locked_tx, locked_rx) = mpsc::channel();
+            let (panic_tx, panic_rx) = mpsc::channel::<()>();
+
+            let writer = thread::spawn({
+                let ptr = ptr.clone();
+                move || {
+                    let _guard = ptr.write::<u32>();
+                    locked_tx.send(()).unwrap();
+                    panic_rx.recv().unwrap();
+                    panic!("poisoning component");
+                }
+            });
+            locked_rx.recv().unwrap();
+
+            // starts waiting before the component is poisoned
+            let waiter = thread::spawn({
+                let ptr = ptr.clone();
+                move || {
+                    if write {
+                        *ptr.write::<u32>() += 1;
+                    } else {
+                        let _ = *ptr.read::<u32>();
+                    }
+                }
+            });
+            thread::sleep(Duration::from_millis(50));
+            panic_tx.send(()).unwrap();
+
+            assert!(writer.join().is_err());
+            assert!(waiter.join().is_err());
+            // the waiter released the lock before panicking
+            assert_eq!(ptr.get_ref().state.load(Ordering::Relaxed), 0);
+            assert_eq!(*ptr.read_poisoned::<u32>(), 42);
+        }
+    }
+
+    #[test]
+    fn test_read_poisoned() {
+        let ptr = ComponentPtr::new(42u32);
+        assert!(!ptr.is_poisoned());
+        poison(&ptr);
+        assert!(ptr.is_poisoned());
+
+        let guard = ptr.read_poisoned::<u32>();
+        assert_eq!(*guard, 13);
+        drop(guard);
+        assert_eq!(ptr.get_ref().state.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_clear_poison() {
+        let ptr = ComponentPtr::new(42u32);
+        poison(&ptr);
+
+        ptr.clear_poison();
+        assert!(!ptr.is_poisoned());
+        *ptr.write::<u32>() += 1;
+        assert_eq!(*ptr.read::<u32>(), 14);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attempted to read poisoned component")]
+    fn test_read_poisoned_component_panics() {
+        let ptr = ComponentPtr::new(42u32);
+        poison(&ptr);
+        let _guard = ptr.read::<u32>();
+    }
+
     #[test]
     fn test_uninit_init_write() {
         let mut ptr = ComponentPtr::uninitialized::<u32>();
@@ -631,4 +1161,57 @@ mod tests {
             LockState::IS_INIT.bits()
         );
     }
+
+    #[test]
+    fn test_try_write_now_reports_holder() {
+        let ptr = ComponentPtr::new(42u32);
+
+        let (guard, location) = (ptr.write::<u32>(), Location::caller());
+        assert_eq!(
+            ComponentWriteGuard::location(&guard).line(),
+            location.line()
+        );
+        match ptr.try_write_now::<u32>() {
+            Err(TryLockError::WouldBlock {
+                held_at: Some(held_at),
+            }) => {
+                assert_eq!(held_at.file(), location.file());
+                assert_eq!(held_at.line(), location.line());
+            }
+            other => panic!(
+                "expected WouldBlock with a location, got {:?}",
+                other.map(|_| ())
+            ),
+        }
+        assert!(matches!(
+            ptr.try_read_now::<u32>(),
+            Err(TryLockError::WouldBlock { held_at: Some(_) })
+        ));
+        drop(guard);
+
+        let reader = ptr.read::<u32>();
+        assert!(matches!(
+            ptr.try_write_now::<u32>(),
+            Err(TryLockError::WouldBlock { held_at: None })
+        ));
+        assert_eq!(*ptr.try_read_now::<u32>().unwrap(), 42);
+        drop(reader);
+
+        *ptr.try_write_now::<u32>().unwrap() += 1;
+        assert_eq!(*ptr.read::<u32>(), 43);
+        assert!(matches!(
+            ptr.try_write_now::<i64>(),
+            Err(TryLockError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_type_mismatch_accessors() {
+        let ptr = ComponentPtr::new(42u32);
+        assert_eq!(ptr.type_name(), "u32");
+
+        let err = ptr.try_read::<i64>().err().unwrap();
+        assert_eq!(err.expected(), "i64");
+        assert_eq!(err.found(), "u32");
+    }
 }
@@ -1,9 +1,8 @@
-use std::{sync::atomic::Ordering, thread};
+use std::{panic::Location, sync::atomic::Ordering};
 
 use crossbeam::utils::Backoff;
-use wgpu::naga::back;
 
-use crate::component::resource::{ComponentInner, ComponentPtr, LockState, check_deadlock};
+use crate::component::resource::{ComponentPtr, LockState, check_deadlock};
 
 /// A guard that provides read access to a component.
 pub struct ComponentReadGuard<T: 'static> {
@@ -19,42 +18,101 @@ impl<T: 'static> ComponentReadGuard<T> {
     /// inner must represent a valid component of type T.
     #[track_caller]
     pub(crate) unsafe fn lock(inner: ComponentPtr) -> Self {
+        if inner.get_ref().flags.load(Ordering::Acquire) & LockState::POISONED.bits() != 0 {
+            panic!("Attempted to read poisoned component");
+        }
+
+        let guard = unsafe { Self::lock_ignore_poison(inner) };
+        // the writer we waited on may have panicked, so check again now that it is gone
+        if guard.inner.is_poisoned() {
+            drop(guard);
+            panic!("Attempted to read poisoned component");
+        }
+        guard
+    }
+
+    /// Creates a new ComponentReadGuard without checking if the component is poisoned.
+    ///
+    /// # Safety
+    ///
+    /// inner must represent a valid component of type T.
+    #[track_caller]
+    pub(crate) unsafe fn lock_ignore_poison(inner: ComponentPtr) -> Self {
         let inner_ref = inner.get_ref();
 
-        if inner_ref.flags.load(Ordering::Relaxed) & !LockState::IS_INIT.bits() != 0 {
+        if inner_ref.flags.load(Ordering::Acquire) & LockState::IS_INIT.bits() == 0 {
             panic!("Attempted to read uninitialized component");
         }
+        debug_assert!(
+            inner.is::<T>(),
+            "ComponentReadGuard::lock: component is {}, not {}",
+            inner_ref.type_name,
+            std::any::type_name::<T>()
+        );
+
+        // the guard owns `inner`, which already keeps the component alive, so only the reader count is touched
+        acquire_read(&inner);
 
-        unsafe {
-            inner.retain();
+        Self {
+            inner,
+            phantom: std::marker::PhantomData,
         }
+    }
+}
 
-        let mut is_first = true;
-        let backoff = Backoff::new();
-        while inner_ref
+impl<T: 'static> ComponentReadGuard<T> {
+    /// Attempts to acquire a read lock without blocking.
+    ///
+    /// If a writer holds the lock, returns where it was acquired.
+    ///
+    /// # Safety
+    ///
+    /// inner must represent a valid, initialized and unpoisoned component of type T.
+    pub(crate) unsafe fn try_lock(
+        inner: ComponentPtr,
+    ) -> Result<Self, Option<&'static Location<'static>>> {
+        let inner_ref = inner.get_ref();
+        if inner_ref
             .state
             .fetch_update(Ordering::Release, Ordering::Acquire, |v| {
-                if v == -1 {
-                    // Since a deadlock indicates a frame higher up in the stack is holding the write lock,
-                    // we can check for it here to provide a better error message.
-                    // If we are deadlocked we will know right away, so we only need to check once.
-                    if is_first {
-                        check_deadlock(&inner_ref, "read");
-                    }
-                    is_first = false;
-                    return None;
-                }
-                Some(v + 1)
+                (v != -1).then_some(v + 1)
             })
             .is_err()
         {
-            backoff.snooze();
+            return Err(inner_ref.writer.location());
         }
 
-        Self {
+        Ok(Self {
             inner,
             phantom: std::marker::PhantomData,
-        }
+        })
+    }
+}
+
+/// Spins until a read lock on `inner` is acquired.
+#[track_caller]
+fn acquire_read(inner: &ComponentPtr) {
+    let inner_ref = inner.get_ref();
+    let mut is_first = true;
+    let backoff = Backoff::new();
+    while inner_ref
+        .state
+        .fetch_update(Ordering::Release, Ordering::Acquire, |v| {
+            if v == -1 {
+                // Since a deadlock indicates a frame higher up in the stack is holding the write lock,
+                // we can check for it here to provide a better error message.
+                // If we are deadlocked we will know right away, so we only need to check once.
+                if is_first {
+                    check_deadlock(inner_ref, "read");
+                }
+                is_first = false;
+                return None;
+            }
+            Some(v + 1)
+        })
+        .is_err()
+    {
+        backoff.snooze();
     }
 }
 
@@ -71,8 +129,78 @@ impl<T> Drop for ComponentReadGuard<T> {
     fn drop(&mut self) {
         let inner = self.inner.get_ref();
         inner.state.fetch_sub(1, Ordering::Release);
-        unsafe {
-            self.inner.release();
+    }
+}
+
+/// Read locks on a set of components, held until dropped.
+///
+/// Created by `ComponentStore::read_barrier`. While the barrier exists, no component in the set can be written, so
+/// they are all observed at the same point in time.
+pub struct ReadBarrier {
+    locks: Vec<ComponentPtr>,
+}
+
+impl ReadBarrier {
+    /// Acquires read locks on all of `ptrs`.
+    ///
+    /// Locks are taken in address order, so two barriers over overlapping sets can't deadlock each other.
+    ///
+    /// # Panics
+    /// Panics if any component is poisoned or uninitialized.
+    #[track_caller]
+    pub(crate) fn lock(mut ptrs: Vec<ComponentPtr>) -> Self {
+        ptrs.sort_by_key(ComponentPtr::addr);
+        ptrs.dedup_by_key(|ptr| ptr.addr());
+
+        let mut barrier = Self {
+            locks: Vec::with_capacity(ptrs.len()),
+        };
+        for ptr in ptrs {
+            let flags = LockState::from_bits_truncate(ptr.get_ref().flags.load(Ordering::Acquire));
+            // locks taken so far are released by the barrier's drop
+            if flags.contains(LockState::POISONED) {
+                panic!("Attempted to read poisoned component {}", ptr.type_name());
+            }
+            if !flags.contains(LockState::IS_INIT) {
+                panic!(
+                    "Attempted to read uninitialized component {}",
+                    ptr.type_name()
+                );
+            }
+            acquire_read(&ptr);
+            barrier.locks.push(ptr);
+
+            // the writer we waited on may have panicked
+            let ptr = barrier.locks.last().unwrap();
+            if ptr.is_poisoned() {
+                panic!("Attempted to read poisoned component {}", ptr.type_name());
+            }
+        }
+        barrier
+    }
+
+    /// Returns the component of type T, if it is part of the barrier.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        let ptr = self.locks.iter().find(|ptr| ptr.is::<T>())?;
+        // SAFETY: the type was just checked, and the read lock is held for as long as self is borrowed.
+        Some(unsafe { &*(ptr.inner_ref() as *const dyn std::any::Any as *const T) })
+    }
+
+    /// Returns the number of components locked by the barrier.
+    pub fn len(&self) -> usize {
+        self.locks.len()
+    }
+
+    /// Returns true if the barrier holds no locks.
+    pub fn is_empty(&self) -> bool {
+        self.locks.is_empty()
+    }
+}
+
+impl Drop for ReadBarrier {
+    fn drop(&mut self) {
+        for ptr in &self.locks {
+            ptr.get_ref().state.fetch_sub(1, Ordering::Release);
         }
     }
 }
@@ -80,7 +208,7 @@ impl<T> Drop for ComponentReadGuard<T> {
 #[cfg(test)]
 mod tests {
 
-    use std::panic;
+    use std::{panic, sync::atomic::Ordering};
 
     use crate::component::resource::{ComponentPtr, read::ComponentReadGuard};
 
@@ -110,6 +238,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_guard_counts_return_to_baseline() {
+        let ptr = ComponentPtr::new(42u32);
+        let inner_ref = ptr.get_ref();
+        let counts = || {
+            (
+                inner_ref.strong.load(Ordering::Relaxed),
+                inner_ref.weak.load(Ordering::Relaxed),
+                inner_ref.state.load(Ordering::Relaxed),
+            )
+        };
+        let baseline = counts();
+
+        for _ in 0..3 {
+            let guard = unsafe { ComponentReadGuard::<u32>::lock(ptr.clone()) };
+            // the only extra strong reference is the guard's own pointer
+            assert_eq!(counts(), (baseline.0 + 1, baseline.1, baseline.2 + 1));
+            drop(guard);
+        }
+
+        let guard = unsafe { ComponentReadGuard::<u32>::try_lock(ptr.clone()) }.unwrap();
+        drop(guard);
+        assert_eq!(counts(), baseline);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "ComponentReadGuard::lock: component is u32, not i64")]
+    fn test_component_read_guard_wrong_type() {
+        let ptr = ComponentPtr::new(42u32);
+        let _guard = unsafe { ComponentReadGuard::<i64>::lock(ptr.clone()) };
+    }
+
     #[test]
     fn test_heavy_multithread() {
         let ptr = ComponentPtr::new(100u32);
@@ -7,10 +7,11 @@ use std::{
 
 use crossbeam::utils::Backoff;
 
-use crate::component::resource::{ComponentPtr, LockState, check_deadlock};
+use crate::component::resource::{ComponentPtr, LockState, check_deadlock, thread_id};
 
 pub struct ComponentWriteGuard<T: 'static> {
     inner: ComponentPtr,
+    location: &'static Location<'static>,
     phantom: std::marker::PhantomData<T>,
 }
 
@@ -22,15 +23,20 @@ impl<T: 'static> ComponentWriteGuard<T> {
     /// inner must represent a valid component of type T.
     pub(crate) unsafe fn lock(inner: ComponentPtr, location: &'static Location<'static>) -> Self {
         let inner_ref = inner.get_ref();
-        let this = thread::current().id().as_u64().get();
 
-        if inner_ref.flags.load(Ordering::Relaxed) & !LockState::IS_INIT.bits() != 0 {
+        let flags = inner_ref.flags.load(Ordering::Acquire);
+        if flags & LockState::IS_INIT.bits() == 0 {
             panic!("Attempted to write uninitialized component");
         }
-
-        unsafe {
-            inner.retain();
+        if flags & LockState::POISONED.bits() != 0 {
+            panic!("Attempted to write poisoned component");
         }
+        debug_assert!(
+            inner.is::<T>(),
+            "ComponentWriteGuard::lock: component is {}, not {}",
+            inner_ref.type_name,
+            std::any::type_name::<T>()
+        );
 
         let mut is_first = true;
         // wait until we can acquire the write lock
@@ -48,34 +54,82 @@ impl<T: 'static> ComponentWriteGuard<T> {
             backoff.snooze();
         }
 
-        // we have the write lock, set the writer thread id and location
-        inner_ref.writer.0.store(this, Ordering::Relaxed);
-        inner_ref
-            .writer
-            .1
-            .store(location as *const _ as *mut _, Ordering::Relaxed);
+        // the writer we waited on may have panicked, so check again now that it is gone
+        if inner_ref.flags.load(Ordering::Acquire) & LockState::POISONED.bits() != 0 {
+            inner_ref.state.store(0, Ordering::Release);
+            panic!("Attempted to write poisoned component");
+        }
+
+        unsafe { Self::acquired(inner, location) }
+    }
+
+    /// Attempts to acquire the write lock without blocking.
+    ///
+    /// If the lock is held, returns where the current writer acquired it, or None if it is held by readers.
+    ///
+    /// # Safety
+    ///
+    /// inner must represent a valid, initialized and unpoisoned component of type T.
+    pub(crate) unsafe fn try_lock(
+        inner: ComponentPtr,
+        location: &'static Location<'static>,
+    ) -> Result<Self, Option<&'static Location<'static>>> {
+        let inner_ref = inner.get_ref();
+        if let Err(v) =
+            inner_ref
+                .state
+                .compare_exchange(0, -1, Ordering::Acquire, Ordering::Relaxed)
+        {
+            if v != -1 {
+                return Err(None);
+            }
+            return Err(inner_ref.writer.location());
+        }
+
+        Ok(unsafe { Self::acquired(inner, location) })
+    }
+
+    /// Finishes creating a guard once the write lock is held.
+    ///
+    /// # Safety
+    ///
+    /// The write lock must be held.
+    unsafe fn acquired(inner: ComponentPtr, location: &'static Location<'static>) -> Self {
+        let inner_ref = inner.get_ref();
+        let this = thread_id();
+
+        // we have the write lock, publish ourselves as the writer for deadlock reports
+        inner_ref.writer.publish(this, location);
 
         Self {
             inner,
+            location,
             phantom: std::marker::PhantomData,
         }
     }
 }
 
+impl<T> ComponentWriteGuard<T> {
+    /// Returns where this write lock was acquired.
+    ///
+    /// This is an associated function, called as `ComponentWriteGuard::location(&guard)`, so it doesn't shadow a
+    /// `location` method on `T`.
+    pub fn location(this: &Self) -> &'static Location<'static> {
+        this.location
+    }
+}
+
 impl<T> Drop for ComponentWriteGuard<T> {
     fn drop(&mut self) {
         let inner_ref = self.inner.get_ref();
 
-        // clear the writer thread id and location
-        inner_ref.writer.0.store(0, Ordering::Relaxed);
-        inner_ref
-            .writer
-            .1
-            .store(std::ptr::null_mut(), Ordering::Relaxed);
-
-        unsafe {
-            self.inner.release();
+        // a panic while holding the write lock may have left the component half-written
+        if thread::panicking() {
+            self.inner.poison();
         }
+
+        inner_ref.writer.clear();
+
         // release the write lock
         inner_ref.state.store(0, Ordering::Release);
     }
@@ -102,7 +156,7 @@ mod tests {
     use std::{panic::Location, thread};
 
     use crate::component::resource::{
-        ComponentPtr, read::ComponentReadGuard, write::ComponentWriteGuard,
+        ComponentPtr, read::ComponentReadGuard, thread_id, write::ComponentWriteGuard,
     };
 
     #[test]
@@ -136,6 +190,14 @@ mod tests {
         let _guard2 = unsafe { ComponentWriteGuard::<u32>::lock(ptr.clone(), Location::caller()) };
     }
 
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "ComponentWriteGuard::lock: component is u32, not i64")]
+    fn test_component_write_guard_wrong_type() {
+        let ptr = ComponentPtr::new(42u32);
+        let _guard = unsafe { ComponentWriteGuard::<i64>::lock(ptr.clone(), Location::caller()) };
+    }
+
     #[test]
     fn test_component_write_guard_heavy_multithread() {
         let ptr = ComponentPtr::new(0u32);
@@ -145,7 +207,7 @@ mod tests {
         for _ in 0..10 {
             let ptr_clone = ptr.clone();
             let handle = std::thread::spawn(move || {
-                let thread_id = thread::current().id().as_u64().get();
+                let thread_id = thread_id();
                 for i in 0..10000 {
                     let mut guard = unsafe {
                         ComponentWriteGuard::<u32>::lock(ptr_clone.clone(), Location::caller())
@@ -0,0 +1,47 @@
+use std::{
+    panic::Location,
+    sync::atomic::{AtomicPtr, AtomicU64, Ordering},
+};
+
+/// The thread holding a component's write lock, and where it acquired it.
+///
+/// Only `&'static Location`s are ever stored, so the location is always safe to read. It may be stale if it is
+/// read from a thread other than the writer, since the writer can release the lock at any point.
+pub(super) struct WriterSlot {
+    tid: AtomicU64,
+    location: AtomicPtr<Location<'static>>,
+}
+
+impl WriterSlot {
+    pub(super) const fn new() -> Self {
+        Self {
+            tid: AtomicU64::new(0),
+            location: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    /// Records `tid` as the writer. Must only be called while holding the write lock.
+    pub(super) fn publish(&self, tid: u64, location: &'static Location<'static>) {
+        // the location is published first, so anyone who sees the tid also sees it.
+        self.location
+            .store(std::ptr::from_ref(location).cast_mut(), Ordering::Release);
+        self.tid.store(tid, Ordering::Release);
+    }
+
+    /// Clears the writer. Must only be called by the writer, before releasing the write lock.
+    pub(super) fn clear(&self) {
+        self.tid.store(0, Ordering::Release);
+        self.location.store(std::ptr::null_mut(), Ordering::Release);
+    }
+
+    /// Returns the thread id of the writer, or 0 if there is none.
+    pub(super) fn owner(&self) -> u64 {
+        self.tid.load(Ordering::Acquire)
+    }
+
+    /// Returns where the write lock was acquired, if it is held.
+    pub(super) fn location(&self) -> Option<&'static Location<'static>> {
+        // SAFETY: the pointer is either null or came from a `&'static Location` in `publish`.
+        unsafe { self.location.load(Ordering::Acquire).as_ref() }
+    }
+}
@@ -48,6 +48,62 @@ impl TypeMap {
             .and_then(|node| node.data.downcast_mut::<T>())
     }
 
+    /// Retrieves a mutable reference to a resource of the specified type, inserting `T::default()` if it is absent.
+    pub fn get_or_default<T: 'static + Send + Sync + Default>(&mut self) -> &mut T {
+        self.get_or_insert_with(T::default)
+    }
+
+    /// Retrieves a mutable reference to a resource of the specified type, inserting the result of `f` if it is
+    /// absent.
+    pub fn get_or_insert_with<T: 'static + Send + Sync>(
+        &mut self,
+        f: impl FnOnce() -> T,
+    ) -> &mut T {
+        self.map
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| TypeContainer::new(f()))
+            .data
+            .downcast_mut::<T>()
+            .expect("TypeMap entry does not match its TypeId")
+    }
+
+    /// Returns true if the TypeMap contains a resource of the specified type.
+    pub fn contains<T: 'static + Send + Sync>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns the number of resources in the TypeMap.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the TypeMap contains no resources.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns an iterator over the type names of the stored resources, in no particular order.
+    pub fn iter_type_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.map.values().map(|node| node.type_name)
+    }
+
+    /// Moves every resource from `other` into this TypeMap.
+    ///
+    /// If both maps contain a resource of the same type, the one from `other` wins. See `merge_preserving` for the
+    /// opposite policy.
+    pub fn merge(&mut self, other: TypeMap) {
+        self.map.extend(other.map);
+    }
+
+    /// Moves every resource from `other` into this TypeMap, keeping the existing resource on conflicts.
+    ///
+    /// Resources from `other` that conflict are dropped.
+    pub fn merge_preserving(&mut self, other: TypeMap) {
+        for (id, node) in other.map {
+            self.map.entry(id).or_insert(node);
+        }
+    }
+
     /// Clears all resources from the TypeMap.
     pub fn clear(&mut self) {
         self.map.clear();
@@ -91,6 +147,24 @@ impl ImmutableTypeMap {
             .and_then(|node| node.downcast::<T>().ok())
     }
 
+    /// Returns true if the ImmutableTypeMap contains a resource of the specified type.
+    pub fn contains<T: 'static + Send + Sync>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Retrieves an `Arc` to a resource of the specified type, inserting the result of `f` if it is absent.
+    pub fn get_or_insert_with<T: 'static + Send + Sync>(
+        &mut self,
+        f: impl FnOnce() -> T,
+    ) -> Arc<T> {
+        self.map
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Arc::new(f()))
+            .clone()
+            .downcast::<T>()
+            .expect("ImmutableTypeMap entry does not match its TypeId")
+    }
+
     /// Returns an iterator over the TypeIds of the stored resources.
     pub fn keys(&self) -> impl Iterator<Item = &TypeId> {
         self.map.keys()
@@ -116,3 +190,70 @@ impl Debug for TypeContainer {
         write!(f, "<{}>", self.type_name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{ImmutableTypeMap, TypeMap};
+
+    #[test]
+    fn test_type_map_introspection() {
+        let mut map = TypeMap::new();
+        assert!(map.is_empty());
+        assert!(!map.contains::<u32>());
+
+        map.insert(1u32);
+        map.insert(String::from("a"));
+        assert_eq!(map.len(), 2);
+        assert!(map.contains::<u32>());
+        assert!(!map.contains::<i32>());
+
+        let mut names: Vec<_> = map.iter_type_names().collect();
+        names.sort();
+        assert_eq!(names, ["alloc::string::String", "u32"]);
+    }
+
+    fn merge_inputs() -> (TypeMap, TypeMap) {
+        let mut base = TypeMap::new();
+        base.insert(1u32);
+        base.insert(1.0f32);
+
+        let mut other = TypeMap::new();
+        other.insert(2u32);
+        other.insert(String::from("b"));
+        (base, other)
+    }
+
+    #[test]
+    fn test_merge_overwrites() {
+        let (mut base, other) = merge_inputs();
+        base.merge(other);
+        assert_eq!(base.len(), 3);
+        assert_eq!(base.get::<u32>(), Some(&2));
+        assert_eq!(base.get::<f32>(), Some(&1.0));
+        assert_eq!(base.get::<String>().map(String::as_str), Some("b"));
+    }
+
+    #[test]
+    fn test_merge_preserving() {
+        let (mut base, other) = merge_inputs();
+        base.merge_preserving(other);
+        assert_eq!(base.len(), 3);
+        assert_eq!(base.get::<u32>(), Some(&1));
+        assert_eq!(base.get::<String>().map(String::as_str), Some("b"));
+    }
+
+    #[test]
+    fn test_immutable_get_or_insert_with() {
+        let mut map = ImmutableTypeMap::new();
+        assert!(!map.contains::<String>());
+
+        let first = map.get_or_insert_with(|| String::from("config"));
+        assert!(map.contains::<String>());
+
+        let second = map.get_or_insert_with(|| String::from("unused"));
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(*second, "config");
+    }
+}
@@ -1,18 +1,162 @@
 use std::f32::consts;
 
-use glam::{Mat4, Vec2, Vec3, Vec4, vec2};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4, vec2};
 
 #[derive(Clone, Debug)]
 pub struct Camera {
     projection: Mat4,
+    // the parameters `projection` was built from, so the camera can be saved and restored
+    projection_params: Projection,
     view: Mat4,
     pub rot: Vec2,
     pub position: Vec3,
     direction_vector: Vec3,
+    up: Vec3,
+    exposure: f32,
+    auto_exposure: Option<AutoExposure>,
 }
 
 const FOV_Y_RADS: f32 = consts::FRAC_PI_2;
 
+/// The parameters of a camera's projection matrix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Projection {
+    /// A right handed perspective projection.
+    Perspective {
+        /// The vertical field of view, in radians.
+        fov_y: f32,
+        aspect_ratio: f32,
+        z_near: f32,
+        z_far: f32,
+    },
+    /// A right handed orthographic projection, using the OpenGL depth range.
+    Orthographic {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        z_near: f32,
+        z_far: f32,
+    },
+}
+
+impl Projection {
+    /// Builds the projection matrix.
+    pub fn matrix(&self) -> Mat4 {
+        match *self {
+            Projection::Perspective {
+                fov_y,
+                aspect_ratio,
+                z_near,
+                z_far,
+            } => Mat4::perspective_rh(fov_y, aspect_ratio, z_near, z_far),
+            Projection::Orthographic {
+                left,
+                right,
+                bottom,
+                top,
+                z_near,
+                z_far,
+            } => Mat4::orthographic_rh_gl(left, right, bottom, top, z_near, z_far),
+        }
+    }
+}
+
+/// The essential state of a [`Camera`], for saving and restoring viewpoints.
+///
+/// The view and projection matrices aren't stored; they are rebuilt by [`Camera::from_state`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraState {
+    pub position: Vec3,
+    /// (yaw, pitch) in radians.
+    pub rot: Vec2,
+    pub up: Vec3,
+    pub projection: Projection,
+    pub exposure: f32,
+}
+
+/// The position of the world origin on screen for `Camera::pixel_perfect_2d`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Corner {
+    /// Y points down, like window coordinates.
+    #[default]
+    TopLeft,
+    /// Y points down and visible X is negative.
+    TopRight,
+    /// Y points up.
+    BottomLeft,
+    /// Y points up and visible X is negative.
+    BottomRight,
+    /// Y points up. The origin is rounded down to a whole pixel, so odd dimensions don't shift texels by half a pixel.
+    Center,
+}
+
+/// Adapts a camera's exposure towards a target brightness, based on a luminance histogram of the previous frame.
+///
+/// The histogram is expected to bucket pixels by `log2(luminance)`, evenly spread over
+/// `min_log_luminance..max_log_luminance`. It is usually built by a compute pass and read back a frame late.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoExposure {
+    /// The average scene luminance that should map to an exposure of 1.
+    pub target_luminance: f32,
+    /// `log2` of the luminance at the bottom of the first histogram bucket.
+    pub min_log_luminance: f32,
+    /// `log2` of the luminance at the top of the last histogram bucket.
+    pub max_log_luminance: f32,
+    /// How quickly exposure adapts, in 1/seconds. Higher values adapt faster.
+    pub speed: f32,
+    /// The range the adapted exposure is clamped to.
+    pub exposure_range: (f32, f32),
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        Self {
+            target_luminance: 0.18,
+            min_log_luminance: -8.0,
+            max_log_luminance: 4.0,
+            speed: 1.5,
+            exposure_range: (0.01, 100.0),
+        }
+    }
+}
+
+impl AutoExposure {
+    /// Returns the geometric mean luminance described by `histogram`, or `None` if it is empty.
+    pub fn average_luminance(&self, histogram: &[u32]) -> Option<f32> {
+        let total: u64 = histogram.iter().map(|&count| count as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let bucket_width =
+            (self.max_log_luminance - self.min_log_luminance) / histogram.len() as f32;
+        let log_sum: f32 = histogram
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| {
+                let center = self.min_log_luminance + (i as f32 + 0.5) * bucket_width;
+                center * count as f32
+            })
+            .sum();
+        Some((log_sum / total as f32).exp2())
+    }
+
+    /// Returns `exposure` moved towards the exposure that brings `histogram` to the target luminance.
+    pub fn adapt(&self, exposure: f32, histogram: &[u32], delta_time: f32) -> f32 {
+        let Some(average) = self.average_luminance(histogram) else {
+            return exposure;
+        };
+
+        let (min, max) = self.exposure_range;
+        let target = (self.target_luminance / average).clamp(min, max);
+        let t = 1.0 - (-delta_time * self.speed).exp();
+        (exposure + (target - exposure) * t).clamp(min, max)
+    }
+}
+
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: Mat4= Mat4::from_cols(
     Vec4::new(1.0, 0.0, 0.0, 0.0),
@@ -24,15 +168,66 @@ pub const OPENGL_TO_WGPU_MATRIX: Mat4= Mat4::from_cols(
 impl Camera {
     /// Creates a new Camera with the given projection and view matrices.
     pub fn new(aspect_ratio: f32, z_near: f32, z_far: f32) -> Self {
-        let projection = Mat4::perspective_rh(FOV_Y_RADS, aspect_ratio, z_near, z_far);
+        let projection_params = Projection::Perspective {
+            fov_y: FOV_Y_RADS,
+            aspect_ratio,
+            z_near,
+            z_far,
+        };
         let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::ZERO, Vec3::Y);
 
         Self {
-            projection,
+            projection: projection_params.matrix(),
+            projection_params,
             view,
             rot: Vec2::ZERO,
             position: Vec3::ZERO,
             direction_vector: Self::calculate_direction(0.0, 0.0),
+            up: Vec3::Y,
+            exposure: 1.0,
+            auto_exposure: None,
+        }
+    }
+
+    /// Creates an orthographic 2D camera where one world unit is one pixel.
+    ///
+    /// The camera looks down -Z, and the world origin is placed at `origin`. Pixel edges always land on whole units,
+    /// so textures drawn at integer positions are not blurred. The top corners flip the Y axis, which also flips
+    /// triangle winding.
+    pub fn pixel_perfect_2d(width: u32, height: u32, origin: Corner) -> Self {
+        let (w, h) = (width as f32, height as f32);
+        let (left, right, bottom, top) = match origin {
+            Corner::TopLeft => (0.0, w, h, 0.0),
+            Corner::TopRight => (-w, 0.0, h, 0.0),
+            Corner::BottomLeft => (0.0, w, 0.0, h),
+            Corner::BottomRight => (-w, 0.0, 0.0, h),
+            Corner::Center => {
+                let (x, y) = ((width / 2) as f32, (height / 2) as f32);
+                (-x, w - x, -y, h - y)
+            }
+        };
+
+        // OPENGL_TO_WGPU_MATRIX is applied on top, so this uses the OpenGL depth range
+        let projection_params = Projection::Orthographic {
+            left,
+            right,
+            bottom,
+            top,
+            z_near: -1.0,
+            z_far: 1.0,
+        };
+        let direction = Vec3::NEG_Z;
+
+        Self {
+            projection: projection_params.matrix(),
+            projection_params,
+            view: Mat4::look_at_rh(Vec3::ZERO, direction, Vec3::Y),
+            rot: vec2(direction.z.atan2(direction.x), 0.0),
+            position: Vec3::ZERO,
+            direction_vector: direction,
+            up: Vec3::Y,
+            exposure: 1.0,
+            auto_exposure: None,
         }
     }
 
@@ -45,9 +240,75 @@ impl Camera {
         .normalize()
     }
 
+    /// Returns the rotation that takes the +X axis to the direction described by the given yaw and pitch.
+    fn orientation(yaw: f32, pitch: f32) -> Quat {
+        Quat::from_rotation_y(-yaw) * Quat::from_rotation_z(pitch)
+    }
+
+    /// Builds a view matrix looking from `position` towards `target` using the camera's up vector.
+    ///
+    /// If the up vector is parallel to the view direction, a perpendicular vector is used instead.
+    fn view_towards(&self, position: Vec3, target: Vec3) -> Mat4 {
+        let direction = (target - position).normalize_or_zero();
+        let up = if direction.cross(self.up).length_squared() > 1e-6 {
+            self.up
+        } else if direction != Vec3::ZERO {
+            direction.any_orthonormal_vector()
+        } else {
+            self.up
+        };
+        Mat4::look_at_rh(position, target, up)
+    }
+
     /// Resizes the camera's projection matrix.
     pub fn resize(&mut self, aspect_ratio: f32, z_near: f32, z_far: f32) {
-        self.projection = Mat4::perspective_rh(FOV_Y_RADS, aspect_ratio, z_near, z_far);
+        self.set_projection(Projection::Perspective {
+            fov_y: FOV_Y_RADS,
+            aspect_ratio,
+            z_near,
+            z_far,
+        });
+    }
+
+    /// Returns the parameters of the projection matrix.
+    pub fn projection_params(&self) -> Projection {
+        self.projection_params
+    }
+
+    /// Replaces the projection and rebuilds the projection matrix.
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection_params = projection;
+        self.projection = projection.matrix();
+    }
+
+    /// Captures the state needed to rebuild this camera with [`Camera::from_state`].
+    ///
+    /// Auto-exposure settings are not included.
+    pub fn to_state(&self) -> CameraState {
+        CameraState {
+            position: self.position,
+            rot: self.rot,
+            up: self.up,
+            projection: self.projection_params,
+            exposure: self.exposure,
+        }
+    }
+
+    /// Rebuilds a camera from a saved state, recomputing its matrices.
+    pub fn from_state(state: &CameraState) -> Self {
+        let mut camera = Self {
+            projection: state.projection.matrix(),
+            projection_params: state.projection,
+            view: Mat4::IDENTITY,
+            rot: state.rot,
+            position: state.position,
+            direction_vector: Vec3::ZERO,
+            up: state.up.normalize_or(Vec3::Y),
+            exposure: state.exposure,
+            auto_exposure: None,
+        };
+        camera.flush();
+        camera
     }
 
     /// Points the camera in the given yaw and pitch (in radians).
@@ -70,7 +331,16 @@ impl Camera {
         let position = target - direction * 2.0;
 
         self.position = position;
-        self.view = Mat4::look_at_rh(position, target, Vec3::Y);
+        self.view = self.view_towards(position, target);
+    }
+
+    /// Rotates the camera by the given (yaw, pitch) delta in radians, clamping the pitch to `pitch_limits` (min, max).
+    ///
+    /// Clamping the pitch avoids the view flipping over when looking straight up or down.
+    pub fn rotate_clamped(&mut self, delta: Vec2, pitch_limits: (f32, f32)) {
+        self.rot += delta;
+        self.rot.y = self.rot.y.clamp(pitch_limits.0, pitch_limits.1);
+        self.flush();
     }
 
     /// Points the camera to look at the given target position.
@@ -79,14 +349,14 @@ impl Camera {
         self.rot = vec2(direction.z.atan2(direction.x), direction.y.asin());
 
         self.direction_vector = direction;
-        self.view = Mat4::look_at_rh(self.position, target, Vec3::Y);
+        self.view = self.view_towards(self.position, target);
     }
 
     /// Sets the position of the camera.
     pub fn pos(&mut self, position: Vec3) {
         self.position = position;
         let target = position + self.direction_vector;
-        self.view = Mat4::look_at_rh(position, target, Vec3::Y);
+        self.view = self.view_towards(position, target);
     }
 
     /// Returns the projection matrix of the camera.
@@ -109,10 +379,288 @@ impl Camera {
         OPENGL_TO_WGPU_MATRIX * self.projection * self.view
     }
 
+    /// Returns the world space ray through the given point in normalized device coordinates, as
+    /// `(origin, direction)`.
+    ///
+    /// `ndc` ranges from `-1` to `1` on both axes, with `y` pointing up. The origin is the point at depth 0 of
+    /// `projection_view_matrix`, just in front of the camera, and the direction is normalized. To cast many rays at once, use `screen_rays`.
+    pub fn screen_ray(&self, ndc: Vec2) -> (Vec3, Vec3) {
+        self.screen_rays(&[ndc])[0]
+    }
+
+    /// Returns the world space ray through each of the given points in normalized device coordinates, as
+    /// `(origin, direction)`. See `screen_ray`.
+    ///
+    /// The projection-view matrix is inverted once and shared by every ray.
+    pub fn screen_rays(&self, ndcs: &[Vec2]) -> Vec<(Vec3, Vec3)> {
+        let inverse = self.projection_view_matrix().inverse();
+        ndcs.iter()
+            .map(|ndc| {
+                let near = inverse.project_point3(ndc.extend(0.0));
+                let far = inverse.project_point3(ndc.extend(1.0));
+                (near, (far - near).normalize())
+            })
+            .collect()
+    }
+
+    /// Moves the camera towards the pose of `target`.
+    ///
+    /// The position is interpolated linearly and the orientation is slerped. The projection is left untouched, so
+    /// FOV and aspect ratio stay stable during the move. `t` is clamped to `0..=1`.
+    pub fn lerp_towards(&mut self, target: &Camera, t: f32) {
+        let t = t.clamp(0.0, 1.0);
+        self.position = self.position.lerp(target.position, t);
+
+        let from = Self::orientation(self.rot.x, self.rot.y);
+        let to = Self::orientation(target.rot.x, target.rot.y);
+        let direction = (from.slerp(to, t) * Vec3::X).normalize();
+        self.rot = vec2(
+            direction.z.atan2(direction.x),
+            direction.y.clamp(-1.0, 1.0).asin(),
+        );
+
+        self.flush();
+    }
+
+    /// Returns a copy of the camera reflected across the given plane, for planar reflections.
+    ///
+    /// The plane is given as `(normal, distance)`, where points `p` on the plane satisfy `normal.dot(p) + distance == 0`.
+    /// The up vector is kept as-is so the reflected camera stays upright.
+    pub fn mirrored(&self, plane: Vec4) -> Camera {
+        let length = plane.truncate().length();
+        let normal = plane.truncate() / length;
+        let distance = plane.w / length;
+
+        let mut camera = self.clone();
+        camera.position = self.position - 2.0 * (normal.dot(self.position) + distance) * normal;
+
+        let direction = self.direction_vector - 2.0 * normal.dot(self.direction_vector) * normal;
+        camera.direction_vector = direction;
+        camera.rot = vec2(
+            direction.z.atan2(direction.x),
+            direction.y.clamp(-1.0, 1.0).asin(),
+        );
+        camera.view = camera.view_towards(camera.position, camera.position + direction);
+        camera
+    }
+
     /// Flushes the camera's view matrix based on its current position and direction.
     pub fn flush(&mut self) {
         self.direction_vector = Self::calculate_direction(self.rot.x, self.rot.y);
         let target = self.position + self.direction_vector;
-        self.view = Mat4::look_at_rh(self.position, target, Vec3::Y);
+        self.view = self.view_towards(self.position, target);
+    }
+
+    /// Returns the up vector of the camera.
+    pub fn up(&self) -> Vec3 {
+        self.up
+    }
+
+    /// Sets the up vector of the camera and rebuilds the view matrix.
+    ///
+    /// This is useful for gravity-relative cameras, e.g. when walking around a small planet.
+    pub fn set_up(&mut self, up: Vec3) {
+        self.up = up.normalize_or(Vec3::Y);
+        let target = self.position + self.direction_vector;
+        self.view = self.view_towards(self.position, target);
+    }
+
+    /// Returns the exposure multiplier applied before tonemapping. Defaults to 1.
+    ///
+    /// The camera doesn't apply this itself; stash it as [`Exposure`](crate::graphics::pipeline::Exposure) for the
+    /// tonemap pipeline.
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// Sets the exposure multiplier. This is overwritten by `update_exposure` while auto-exposure is enabled.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure;
+    }
+
+    /// Returns the auto-exposure settings, if enabled.
+    pub fn auto_exposure(&self) -> Option<&AutoExposure> {
+        self.auto_exposure.as_ref()
+    }
+
+    /// Enables or disables auto-exposure.
+    pub fn set_auto_exposure(&mut self, auto_exposure: Option<AutoExposure>) {
+        self.auto_exposure = auto_exposure;
+    }
+
+    /// Adapts the exposure from a luminance histogram of the previous frame. Does nothing if auto-exposure is off.
+    pub fn update_exposure(&mut self, histogram: &[u32], delta_time: f32) {
+        if let Some(auto) = &self.auto_exposure {
+            self.exposure = auto.adapt(self.exposure, histogram, delta_time);
+        }
+    }
+}
+
+impl Default for Camera {
+    /// Creates a 16:9 perspective camera at the origin looking down -Z, with `z_near = 0.1` and `z_far = 1000.0`.
+    fn default() -> Self {
+        let mut camera = Self::new(16.0 / 9.0, 0.1, 1000.0);
+        let direction = Vec3::NEG_Z;
+        camera.rot = vec2(direction.z.atan2(direction.x), 0.0);
+        camera.direction_vector = direction;
+        camera.view = camera.view_towards(camera.position, camera.position + direction);
+        camera
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec3, Vec4, vec2};
+
+    use super::{AutoExposure, Camera, Corner, Projection};
+
+    #[test]
+    fn test_rotate_clamped_saturates() {
+        let limit = 45.0_f32.to_radians();
+        let mut camera = Camera::new(16.0 / 9.0, 0.1, 100.0);
+
+        camera.rotate_clamped(vec2(0.0, 2.0), (-limit, limit));
+        assert!((camera.rot.y - limit).abs() < 1e-6);
+
+        camera.rotate_clamped(vec2(0.0, -5.0), (-limit, limit));
+        assert!((camera.rot.y + limit).abs() < 1e-6);
+        assert!((camera.front().y - (-limit).sin()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_screen_rays() {
+        let mut camera = Camera::new(16.0 / 9.0, 0.1, 100.0);
+        camera.pos(Vec3::new(1.0, 2.0, 3.0));
+        camera.look_at(Vec3::new(4.0, 2.0, -1.0));
+
+        let (origin, direction) = camera.screen_ray(vec2(0.0, 0.0));
+        assert!(direction.abs_diff_eq(camera.front(), 1e-4));
+        assert!((origin - Vec3::new(1.0, 2.0, 3.0)).length() < 0.2);
+
+        // the batch matches casting each ray on its own
+        let ndcs = [vec2(-1.0, -1.0), vec2(0.5, 0.25), vec2(1.0, 1.0)];
+        let rays = camera.screen_rays(&ndcs);
+        assert_eq!(rays.len(), 3);
+        for (ndc, (origin, direction)) in ndcs.iter().zip(rays) {
+            let (single_origin, single_direction) = camera.screen_ray(*ndc);
+            assert!(origin.abs_diff_eq(single_origin, 1e-5));
+            assert!(direction.abs_diff_eq(single_direction, 1e-5));
+        }
+
+        let (_, top) = camera.screen_ray(vec2(0.0, 1.0));
+        assert!(top.y > camera.front().y);
+    }
+
+    #[test]
+    fn test_pixel_perfect_2d_maps_pixels() {
+        let camera = Camera::pixel_perfect_2d(800, 600, Corner::TopLeft);
+        let clip = camera.projection_view_matrix() * Vec4::new(10.0, 20.0, 0.0, 1.0);
+        assert!((clip.x - (10.0 / 800.0 * 2.0 - 1.0)).abs() < 1e-5);
+        assert!((clip.y - (1.0 - 20.0 / 600.0 * 2.0)).abs() < 1e-5);
+        assert!((0.0..=1.0).contains(&clip.z));
+
+        // odd dimensions keep the center on a pixel edge
+        let camera = Camera::pixel_perfect_2d(801, 601, Corner::Center);
+        let clip = camera.projection_view_matrix() * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        let pixel = ((clip.x + 1.0) / 2.0 * 801.0, (1.0 - clip.y) / 2.0 * 601.0);
+        assert!((pixel.0 - 400.0).abs() < 1e-3);
+        assert!((pixel.1 - 301.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_default_camera() {
+        let camera = Camera::default();
+        assert!(camera.projection().is_finite());
+        assert!(camera.view().is_finite());
+        assert!(camera.projection_view_matrix().is_finite());
+
+        let projection = camera.projection();
+        let aspect = projection.y_axis.y / projection.x_axis.x;
+        assert!((aspect - 16.0 / 9.0).abs() < 1e-5);
+        assert!((camera.front() - Vec3::NEG_Z).length() < 1e-6);
+    }
+
+    #[test]
+    fn test_mirrored_across_xz_plane() {
+        let mut camera = Camera::new(16.0 / 9.0, 0.1, 100.0);
+        camera.position = Vec3::new(1.0, 3.0, 2.0);
+        camera.rot = vec2(0.3, 0.4);
+        camera.flush();
+
+        let mirrored = camera.mirrored(Vec4::new(0.0, 1.0, 0.0, 0.0));
+
+        assert!((mirrored.position - Vec3::new(1.0, -3.0, 2.0)).length() < 1e-5);
+        assert!((mirrored.front().y + camera.front().y).abs() < 1e-5);
+        assert!((mirrored.front().x - camera.front().x).abs() < 1e-5);
+        assert!((mirrored.front().z - camera.front().z).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_manual_exposure() {
+        let mut camera = Camera::default();
+        assert_eq!(camera.exposure(), 1.0);
+
+        camera.set_exposure(2.5);
+        assert_eq!(camera.exposure(), 2.5);
+
+        // without auto-exposure, histograms are ignored
+        camera.update_exposure(&[0, 100, 0], 1.0);
+        assert_eq!(camera.exposure(), 2.5);
+    }
+
+    #[test]
+    fn test_auto_exposure_moves_towards_target() {
+        let auto = AutoExposure {
+            target_luminance: 0.25,
+            min_log_luminance: -3.5,
+            max_log_luminance: 4.5,
+            ..Default::default()
+        };
+        // every pixel in the bucket centered on log2(luminance) = 1, so the average luminance is 2
+        let histogram = [0, 0, 0, 0, 100, 0, 0, 0];
+        assert!((auto.average_luminance(&histogram).unwrap() - 2.0).abs() < 1e-5);
+        let target = 0.25 / 2.0;
+
+        let mut camera = Camera::default();
+        camera.set_auto_exposure(Some(auto));
+
+        let mut distance = (camera.exposure() - target).abs();
+        for _ in 0..60 {
+            camera.update_exposure(&histogram, 1.0 / 60.0);
+            let next = (camera.exposure() - target).abs();
+            assert!(next < distance);
+            distance = next;
+        }
+
+        for _ in 0..600 {
+            camera.update_exposure(&histogram, 1.0 / 60.0);
+        }
+        assert!((camera.exposure() - target).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut camera = Camera::new(4.0 / 3.0, 0.5, 250.0);
+        camera.pos(Vec3::new(3.0, 4.0, -2.0));
+        camera.rotate_clamped(vec2(0.7, 0.3), (-1.5, 1.5));
+        camera.set_exposure(2.0);
+
+        let state = camera.to_state();
+        assert!(matches!(
+            state.projection,
+            Projection::Perspective { z_far, .. } if z_far == 250.0
+        ));
+
+        let restored = Camera::from_state(&state);
+        assert_eq!(restored.to_state(), state);
+        assert!(
+            restored
+                .projection_view_matrix()
+                .abs_diff_eq(camera.projection_view_matrix(), 1e-5)
+        );
+
+        let ortho = Camera::pixel_perfect_2d(320, 240, Corner::Center);
+        let restored = Camera::from_state(&ortho.to_state());
+        assert!(restored.projection().abs_diff_eq(ortho.projection(), 1e-6));
     }
 }
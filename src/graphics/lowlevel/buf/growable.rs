@@ -0,0 +1,158 @@
+use bytemuck::Pod;
+
+use crate::{
+    component::{ComponentHandle, ComponentStore},
+    graphics::lowlevel::{WgpuRenderer, memory::TrackedAllocation},
+};
+
+/// A uniform or storage buffer holding a slice of T, which reallocates when written past its capacity.
+///
+/// Reallocation replaces the underlying `wgpu::Buffer`, so any bind groups created before it must be rebuilt.
+/// `write_slice` reports when this happens.
+#[derive(Clone, Debug)]
+pub struct GrowableBuffer<T>
+where
+    T: Pod,
+{
+    label: Option<String>,
+    buffer: wgpu::Buffer,
+    ty: wgpu::BufferBindingType,
+    // capacity and length, in elements
+    capacity: usize,
+    len: usize,
+    handle: ComponentHandle<WgpuRenderer>,
+    _allocation: TrackedAllocation,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Pod> GrowableBuffer<T> {
+    /// Creates a growable uniform buffer with room for `capacity` elements.
+    ///
+    /// Note that WGSL requires elements of uniform arrays to be 16-byte aligned.
+    pub fn new_uniform(state: &ComponentStore, capacity: usize, label: Option<&str>) -> Self {
+        Self::new(state, wgpu::BufferBindingType::Uniform, capacity, label)
+    }
+
+    /// Creates a growable read-only storage buffer with room for `capacity` elements.
+    pub fn new_storage(state: &ComponentStore, capacity: usize, label: Option<&str>) -> Self {
+        let ty = wgpu::BufferBindingType::Storage { read_only: true };
+        Self::new(state, ty, capacity, label)
+    }
+
+    fn new(
+        state: &ComponentStore,
+        ty: wgpu::BufferBindingType,
+        capacity: usize,
+        label: Option<&str>,
+    ) -> Self {
+        // wgpu doesn't allow binding empty buffers
+        let capacity = capacity.max(1);
+        let handle = state.handle_for::<WgpuRenderer>();
+        let buffer = Self::create_buffer(&handle.read(), ty, capacity, label);
+
+        Self {
+            label: label.map(|s| s.to_string()),
+            _allocation: TrackedAllocation::buffer(&buffer),
+            buffer,
+            ty,
+            capacity,
+            len: 0,
+            handle,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn create_buffer(
+        wgpu: &WgpuRenderer,
+        ty: wgpu::BufferBindingType,
+        capacity: usize,
+        label: Option<&str>,
+    ) -> wgpu::Buffer {
+        let usage = match ty {
+            wgpu::BufferBindingType::Uniform => wgpu::BufferUsages::UNIFORM,
+            wgpu::BufferBindingType::Storage { .. } => wgpu::BufferUsages::STORAGE,
+        };
+
+        wgpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label,
+            size: (capacity * std::mem::size_of::<T>()) as u64,
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Returns the underlying wgpu::Buffer. This changes when the buffer is reallocated.
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Returns the number of elements written by the last `write_slice`.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the last `write_slice` was empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of elements the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Writes `data` to the start of the buffer.
+    ///
+    /// If `data` doesn't fit, the buffer is recreated with the next power-of-two capacity and true is returned.
+    /// Bind groups created before a reallocation still point at the old buffer and must be rebuilt.
+    pub fn write_slice(&mut self, data: &[T]) -> bool {
+        let wgpu = self.handle.read();
+
+        let reallocated = data.len() > self.capacity;
+        if reallocated {
+            self.capacity = data.len().next_power_of_two();
+            self.buffer = Self::create_buffer(&wgpu, self.ty, self.capacity, self.label.as_deref());
+            self._allocation = TrackedAllocation::buffer(&self.buffer);
+        }
+
+        self.len = data.len();
+        wgpu.queue
+            .write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
+        reallocated
+    }
+
+    /// Creates a bind group layout for the buffer.
+    pub fn bind_group_layout(&self, binding: u32) -> wgpu::BindGroupLayout {
+        let wgpu = self.handle.read();
+        wgpu.bind_group_layout(
+            self.label.as_deref(),
+            &[wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: self.ty,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        )
+    }
+
+    /// Creates a bind group for the current buffer. This must be called again after a reallocation.
+    pub fn bind_group(&self, binding: u32) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+        let wgpu = self.handle.read();
+        let layout = self.bind_group_layout(binding);
+        (
+            layout.clone(),
+            wgpu.bind_group(
+                self.label.as_deref(),
+                &layout,
+                &[wgpu::BindGroupEntry {
+                    binding,
+                    resource: self.buffer.as_entire_binding(),
+                }],
+            ),
+        )
+    }
+}
@@ -2,6 +2,8 @@ use std::ops::RangeBounds;
 
 use bytemuck::{Pod, Zeroable};
 
+use crate::graphics::lowlevel::memory::TrackedAllocation;
+
 #[derive(Debug, Clone)]
 pub struct IndexBuffer<T>
 where
@@ -9,6 +11,7 @@ where
 {
     buffer: wgpu::Buffer,
     count: usize,
+    _allocation: TrackedAllocation,
     _marker: std::marker::PhantomData<T>,
 }
 
@@ -20,6 +23,7 @@ impl<T: IndexLayout> IndexBuffer<T> {
     /// The caller must ensure that the provided buffer is valid for the type T.
     pub unsafe fn from_raw_parts(buffer: wgpu::Buffer, count: usize) -> Self {
         Self {
+            _allocation: TrackedAllocation::buffer(&buffer),
             buffer,
             count,
             _marker: std::marker::PhantomData,
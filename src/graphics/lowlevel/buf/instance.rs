@@ -0,0 +1,127 @@
+use std::{marker::PhantomData, ops::Range};
+
+use wgpu::VertexBufferLayout;
+
+use crate::{
+    component::{ComponentHandle, ComponentStore},
+    graphics::lowlevel::{WgpuRenderer, buf::VertexLayout, memory::TrackedAllocation},
+};
+
+/// A vertex buffer of per-instance data, which grows when written past its capacity.
+///
+/// `I::LAYOUT` must use `VertexStepMode::Instance`. The buffer is usually bound at slot 1, next to the mesh's
+/// vertices at slot 0 (see `Mesh::draw_instances`).
+#[derive(Debug, Clone)]
+pub struct InstanceBuffer<I>
+where
+    I: VertexLayout,
+{
+    label: Option<String>,
+    buffer: wgpu::Buffer,
+    // capacity and count, in instances
+    capacity: usize,
+    count: usize,
+    handle: ComponentHandle<WgpuRenderer>,
+    _allocation: TrackedAllocation,
+    _marker: PhantomData<I>,
+}
+
+impl<I: VertexLayout> InstanceBuffer<I> {
+    /// The layout of the instance buffer.
+    pub const LAYOUT: VertexBufferLayout<'static> = I::LAYOUT;
+
+    /// Creates an instance buffer holding `data`.
+    ///
+    /// # Panics
+    /// Panics if `I::LAYOUT` doesn't use `VertexStepMode::Instance`.
+    pub fn new(state: &ComponentStore, data: &[I], label: Option<&str>) -> Self {
+        let mut this = Self::with_capacity(state, data.len(), label);
+        this.write_slice(data);
+        this
+    }
+
+    /// Creates an empty instance buffer with room for `capacity` instances.
+    ///
+    /// # Panics
+    /// Panics if `I::LAYOUT` doesn't use `VertexStepMode::Instance`.
+    pub fn with_capacity(state: &ComponentStore, capacity: usize, label: Option<&str>) -> Self {
+        assert_eq!(
+            I::LAYOUT.step_mode,
+            wgpu::VertexStepMode::Instance,
+            "InstanceBuffer layout must use VertexStepMode::Instance"
+        );
+
+        // wgpu doesn't allow empty vertex buffers to be bound
+        let capacity = capacity.max(1);
+        let handle = state.handle_for::<WgpuRenderer>();
+        let buffer = Self::create_buffer(&handle.read(), capacity, label);
+
+        Self {
+            label: label.map(|s| s.to_string()),
+            _allocation: TrackedAllocation::buffer(&buffer),
+            buffer,
+            capacity,
+            count: 0,
+            handle,
+            _marker: PhantomData,
+        }
+    }
+
+    fn create_buffer(wgpu: &WgpuRenderer, capacity: usize, label: Option<&str>) -> wgpu::Buffer {
+        wgpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label,
+            size: (capacity * std::mem::size_of::<I>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Writes `data` to the start of the buffer, replacing the previous instances.
+    ///
+    /// If `data` doesn't fit, the buffer is recreated with the next power-of-two capacity and true is returned.
+    pub fn write_slice(&mut self, data: &[I]) -> bool {
+        let wgpu = self.handle.read();
+
+        let reallocated = data.len() > self.capacity;
+        if reallocated {
+            self.capacity = data.len().next_power_of_two();
+            self.buffer = Self::create_buffer(&wgpu, self.capacity, self.label.as_deref());
+            self._allocation = TrackedAllocation::buffer(&self.buffer);
+        }
+
+        self.count = data.len();
+        wgpu.queue
+            .write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
+        reallocated
+    }
+
+    /// Returns the underlying wgpu::Buffer. This changes when the buffer is reallocated.
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Returns the number of instances written by the last `write_slice`.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the number of instances the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the range of instances to pass to `draw` or `draw_indexed`.
+    pub fn instances(&self) -> Range<u32> {
+        0..self.count as u32
+    }
+
+    /// Sets the written instances on the given render pass at the specified slot.
+    pub fn set_on(&self, pass: &mut wgpu::RenderPass<'_>, slot: u32) {
+        // slices can't be empty, so an empty buffer is bound whole. nothing should be drawn from it anyway
+        let size = (self.count * std::mem::size_of::<I>()) as u64;
+        match size {
+            0 => pass.set_vertex_buffer(slot, self.buffer.slice(..)),
+            size => pass.set_vertex_buffer(slot, self.buffer.slice(..size)),
+        }
+    }
+}
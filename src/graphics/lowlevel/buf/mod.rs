@@ -6,3 +6,15 @@ pub use index::{IndexBuffer, IndexLayout};
 
 mod uniform;
 pub use uniform::UniformBuffer;
+
+mod storage;
+pub use storage::StorageBuffer;
+
+mod growable;
+pub use growable::GrowableBuffer;
+
+mod instance;
+pub use instance::InstanceBuffer;
+
+mod tracked;
+pub use tracked::Tracked;
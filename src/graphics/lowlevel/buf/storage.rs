@@ -0,0 +1,134 @@
+use bytemuck::Pod;
+
+use crate::{
+    component::{ComponentHandle, ComponentStoreHandle},
+    graphics::lowlevel::{WgpuRenderer, memory::TrackedAllocation},
+};
+
+/// A buffer for storage data, holding one or more values of T.
+#[derive(Clone, Debug)]
+pub struct StorageBuffer<T>
+where
+    T: Pod,
+{
+    label: Option<String>,
+    buffer: wgpu::Buffer,
+    handle: ComponentHandle<WgpuRenderer>,
+    _allocation: TrackedAllocation,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Pod> StorageBuffer<T> {
+    /// Creates a new StorageBuffer from a wgpu::Buffer.
+    ///
+    /// This function will panic if the buffer size is smaller than the size of type T.
+    ///
+    /// # Safety
+    /// The caller must ensure that the provided buffer is valid for the type T, and has `STORAGE` usage.
+    pub unsafe fn from_raw_parts(
+        label: Option<&str>,
+        buffer: wgpu::Buffer,
+        handle: ComponentStoreHandle,
+    ) -> Self {
+        assert!(
+            buffer.size() as usize >= std::mem::size_of::<T>(),
+            "Buffer size is smaller than type T"
+        );
+        Self {
+            _allocation: TrackedAllocation::buffer(&buffer),
+            buffer,
+            _marker: std::marker::PhantomData,
+            handle: handle.handle_for::<WgpuRenderer>(),
+            label: label.map(|s| s.to_string()),
+        }
+    }
+
+    /// Returns the underlying wgpu::Buffer.
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Returns the label of the storage buffer, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns the number of values of T the buffer can hold.
+    pub fn len(&self) -> usize {
+        self.buffer.size() as usize / std::mem::size_of::<T>()
+    }
+
+    /// Returns true if the buffer can't hold any values of T.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Creates a bind group layout for the storage buffer.
+    ///
+    /// Writable storage buffers can't be bound in vertex shaders, so they are only visible to fragment and compute.
+    pub fn bind_group_layout(&self, binding: u32, read_only: bool) -> wgpu::BindGroupLayout {
+        let visibility = if read_only {
+            wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE
+        } else {
+            wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE
+        };
+
+        let wgpu = self.handle.get();
+        wgpu.bind_group_layout(
+            self.label.as_deref(),
+            &[wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        )
+    }
+
+    /// Creates a bind group for the storage buffer.
+    pub fn bind_group(
+        &self,
+        binding: u32,
+        read_only: bool,
+    ) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+        let wgpu = self.handle.get();
+        let layout = self.bind_group_layout(binding, read_only);
+        (
+            layout.clone(),
+            wgpu.bind_group(
+                self.label.as_deref(),
+                &layout,
+                &[wgpu::BindGroupEntry {
+                    binding,
+                    resource: self.buffer.as_entire_binding(),
+                }],
+            ),
+        )
+    }
+
+    /// Writes a single value to the start of the storage buffer.
+    pub fn write(&self, data: &T) {
+        self.write_slice(std::slice::from_ref(data));
+    }
+
+    /// Writes values to the start of the storage buffer.
+    ///
+    /// # Panics
+    /// Panics if `data` is longer than the buffer.
+    pub fn write_slice(&self, data: &[T]) {
+        assert!(
+            data.len() <= self.len(),
+            "Attempted to write {} values into a storage buffer of {}",
+            data.len(),
+            self.len()
+        );
+        self.handle
+            .get()
+            .queue
+            .write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
+    }
+}
@@ -0,0 +1,87 @@
+use bytemuck::Pod;
+
+use crate::graphics::lowlevel::buf::UniformBuffer;
+
+/// A value that remembers whether it changed since it was last uploaded to a `UniformBuffer`.
+///
+/// Values are compared byte-wise, so setting the same value again doesn't mark it dirty. This lets per-frame code
+/// call `flush_if_dirty` unconditionally without issuing a `write_buffer` on frames where nothing changed.
+#[derive(Clone, Copy, Debug)]
+pub struct Tracked<T: Pod> {
+    value: T,
+    dirty: bool,
+}
+
+impl<T: Pod> Tracked<T> {
+    /// Creates a tracked value that hasn't been uploaded yet, so the first flush writes it.
+    pub fn new(value: T) -> Self {
+        Self { value, dirty: true }
+    }
+
+    /// Creates a tracked value that is already in the buffer, e.g. because the buffer was created from it.
+    pub fn clean(value: T) -> Self {
+        Self {
+            value,
+            dirty: false,
+        }
+    }
+
+    /// Returns the current value.
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// Sets the value, marking it dirty if it differs from the current one.
+    pub fn set(&mut self, value: T) {
+        if bytemuck::bytes_of(&value) != bytemuck::bytes_of(&self.value) {
+            self.value = value;
+            self.dirty = true;
+        }
+    }
+
+    /// Returns true if the value changed since the last flush.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Forces the next flush to upload the value, e.g. after the buffer was recreated.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Returns the value if it is dirty, and marks it clean.
+    pub fn take_dirty(&mut self) -> Option<&T> {
+        std::mem::take(&mut self.dirty).then_some(&self.value)
+    }
+
+    /// Writes the value to `buffer` if it changed since the last flush. Returns true if anything was written.
+    pub fn flush_if_dirty(&mut self, buffer: &UniformBuffer<T>) -> bool {
+        match self.take_dirty() {
+            Some(value) => {
+                buffer.write(value);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tracked;
+
+    #[test]
+    fn test_tracked_only_dirty_on_change() {
+        let mut tracked = Tracked::clean([1.0f32, 2.0]);
+        assert!(tracked.take_dirty().is_none());
+
+        tracked.set([1.0, 2.0]);
+        assert!(!tracked.is_dirty());
+
+        tracked.set([1.0, 3.0]);
+        assert_eq!(tracked.take_dirty(), Some(&[1.0, 3.0]));
+        assert!(tracked.take_dirty().is_none());
+
+        assert!(Tracked::new(0u32).is_dirty());
+    }
+}
@@ -1,11 +1,15 @@
 use bytemuck::Pod;
+use wgpu::util::DeviceExt;
 
 use crate::{
-    component::{ComponentHandle, ComponentStoreHandle},
-    graphics::lowlevel::WgpuRenderer,
+    component::{ComponentHandle, ComponentStore, ComponentStoreHandle},
+    graphics::lowlevel::{WgpuRenderer, memory::TrackedAllocation},
 };
 
 /// A buffer for uniform data.
+///
+/// This either holds a single `T`, or (when created with `new_array`) an array of `T`s in aligned slots that are
+/// selected with a dynamic offset when binding.
 #[derive(Clone, Debug)]
 pub struct UniformBuffer<T>
 where
@@ -13,7 +17,11 @@ where
 {
     label: Option<String>,
     buffer: wgpu::Buffer,
+    // distance between slots, in bytes. only meaningful for arrays
+    stride: u64,
+    count: u32,
     handle: ComponentHandle<WgpuRenderer>,
+    _allocation: TrackedAllocation,
     _marker: std::marker::PhantomData<T>,
 }
 
@@ -35,6 +43,9 @@ impl<T: Pod> UniformBuffer<T> {
             "Buffer size is smaller than type T"
         );
         Self {
+            stride: buffer.size(),
+            count: 1,
+            _allocation: TrackedAllocation::buffer(&buffer),
             buffer,
             _marker: std::marker::PhantomData,
             handle: handle.handle_for::<WgpuRenderer>(),
@@ -42,11 +53,99 @@ impl<T: Pod> UniformBuffer<T> {
         }
     }
 
+    /// Creates a uniform buffer holding `data` that can be read back with `read`.
+    pub fn new_readable(state: &ComponentStore, data: &T, label: Option<&str>) -> Self {
+        let wgpu = state.get::<WgpuRenderer>();
+        let buffer = wgpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label,
+                contents: bytemuck::bytes_of(data),
+                usage: wgpu::BufferUsages::UNIFORM
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+            });
+
+        // Safety: The buffer is valid for type T as it was created from a T.
+        unsafe { Self::from_raw_parts(label, buffer, state.clone()) }
+    }
+
+    /// Creates a uniform buffer holding `count` values of T, each in a slot aligned to
+    /// `min_uniform_buffer_offset_alignment`.
+    ///
+    /// Slots are written with `write_at`, and selected when drawing by passing `offset(index)` as the dynamic offset
+    /// of a bind group from `bind_group_dynamic`. The contents start zeroed.
+    ///
+    /// # Panics
+    /// Panics if `count` is zero.
+    pub fn new_array(state: &ComponentStore, count: u32, label: Option<&str>) -> Self {
+        assert!(
+            count > 0,
+            "UniformBuffer array must hold at least one value"
+        );
+
+        let wgpu = state.get::<WgpuRenderer>();
+        let alignment = wgpu.device.limits().min_uniform_buffer_offset_alignment as u64;
+        let stride = (std::mem::size_of::<T>() as u64).next_multiple_of(alignment);
+
+        let buffer = wgpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label,
+            size: stride * count as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            label: label.map(|s| s.to_string()),
+            _allocation: TrackedAllocation::buffer(&buffer),
+            buffer,
+            stride,
+            count,
+            handle: state.handle_for::<WgpuRenderer>(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns the number of values the buffer holds. This is 1 unless created with `new_array`.
+    pub fn len(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns true if the buffer holds no values. This is never the case, but is provided for completeness.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the dynamic offset of the slot at `index`, for use with `bind_group_dynamic`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn offset(&self, index: u32) -> u32 {
+        assert!(
+            index < self.count,
+            "UniformBuffer index {index} out of bounds (len {})",
+            self.count
+        );
+        (index as u64 * self.stride) as u32
+    }
+
     /// Returns the underlying wgpu::Buffer.
     pub fn buffer(&self) -> &wgpu::Buffer {
         &self.buffer
     }
 
+    /// Returns the label of the uniform buffer, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Sets the label of the uniform buffer.
+    ///
+    /// wgpu labels are fixed at creation, so this only affects bind groups and layouts created after the call.
+    pub fn set_label(&mut self, label: Option<&str>) {
+        self.label = label.map(|s| s.to_string());
+    }
+
     /// Creates a bind group layout for the uniform buffer.
     pub fn bind_group_layout(&self, binding: u32) -> wgpu::BindGroupLayout {
         let wgpu = self.handle.get();
@@ -82,6 +181,111 @@ impl<T: Pod> UniformBuffer<T> {
         )
     }
 
+    /// Creates a bind group layout for the uniform buffer, binding a single slot selected by a dynamic offset.
+    pub fn bind_group_layout_dynamic(&self, binding: u32) -> wgpu::BindGroupLayout {
+        let wgpu = self.handle.get();
+        wgpu.bind_group_layout(
+            self.label.as_deref(),
+            &[wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+                },
+                count: None,
+            }],
+        )
+    }
+
+    /// Creates a bind group for a single slot of the uniform buffer, selected by a dynamic offset.
+    ///
+    /// Pass `offset(index)` as the dynamic offset when setting the bind group.
+    pub fn bind_group_dynamic(&self, binding: u32) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+        let wgpu = self.handle.get();
+        let layout = self.bind_group_layout_dynamic(binding);
+        (
+            layout.clone(),
+            wgpu.bind_group(
+                self.label.as_deref(),
+                &layout,
+                &[wgpu::BindGroupEntry {
+                    binding,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &self.buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+                    }),
+                }],
+            ),
+        )
+    }
+
+    /// Writes data to the slot at `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn write_at(&self, index: u32, data: &T) {
+        let offset = self.offset(index) as u64;
+        self.handle
+            .get()
+            .queue
+            .write_buffer(&self.buffer, offset, bytemuck::bytes_of(data));
+    }
+
+    /// Reads the (first) value back from the GPU.
+    ///
+    /// The buffer is copied into a staging buffer once all previously submitted work is done. The returned future
+    /// waits on the mapping, so the device must be polled (see `WgpuRenderer::poll`) for it to complete on native.
+    ///
+    /// # Panics
+    /// Panics if the buffer was not created with `COPY_SRC` usage (see `new_readable`). The future panics if the
+    /// staging buffer could not be mapped.
+    pub fn read(&self) -> impl Future<Output = T> + Send + 'static {
+        assert!(
+            self.buffer.usage().contains(wgpu::BufferUsages::COPY_SRC),
+            "UniformBuffer must be created with COPY_SRC usage to be read back"
+        );
+
+        let wgpu = self.handle.get();
+        let size = std::mem::size_of::<T>() as u64;
+        // copies must be a multiple of 4 bytes
+        let staging = wgpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("uniform readback buffer"),
+            size: size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = wgpu.create_encoder(Some("uniform readback encoder"));
+        let copy_size = staging.size().min(self.buffer.size());
+        encoder.copy_buffer_to_buffer(&self.buffer, 0, &staging, 0, copy_size);
+        wgpu.submit_single(encoder.finish());
+
+        let data = wgpu.read_buffer(&staging);
+        async move {
+            let data = data.await.expect("Failed to map uniform readback buffer");
+            bytemuck::pod_read_unaligned(&data[..size as usize])
+        }
+    }
+
+    /// Writes data to the uniform buffer through a staging belt, recording the copy into `encoder`.
+    ///
+    /// This batches well when writing many uniforms per frame. See `WgpuRenderer::staging_belt` for how the belt
+    /// must be finished and recalled.
+    pub fn write_staged(
+        &self,
+        belt: &mut wgpu::util::StagingBelt,
+        encoder: &mut wgpu::CommandEncoder,
+        data: &T,
+    ) {
+        let bytes = bytemuck::bytes_of(data);
+        let size = wgpu::BufferSize::new(bytes.len() as u64).expect("Uniform type is zero sized");
+        belt.write_buffer(encoder, &self.buffer, 0, size)
+            .copy_from_slice(bytes);
+    }
+
     /// Writes data to the uniform buffer.
     pub fn write(&self, data: &T) {
         self.handle
@@ -90,3 +294,33 @@ impl<T: Pod> UniformBuffer<T> {
             .write_buffer(&self.buffer, 0, bytemuck::bytes_of(data));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::UniformBuffer;
+    use crate::{component::ComponentStore, graphics::lowlevel::test_device};
+
+    #[test]
+    fn test_label_round_trip() {
+        let Some((device, _queue)) = test_device() else {
+            return;
+        };
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("camera"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+
+        let store = ComponentStore::new();
+        // Safety: the buffer is large enough for a [f32; 4].
+        let mut uniform =
+            unsafe { UniformBuffer::<[f32; 4]>::from_raw_parts(Some("camera"), buffer, store) };
+        assert_eq!(uniform.label(), Some("camera"));
+
+        uniform.set_label(Some("light"));
+        assert_eq!(uniform.label(), Some("light"));
+        uniform.set_label(None);
+        assert_eq!(uniform.label(), None);
+    }
+}
@@ -2,12 +2,17 @@ use std::{marker::PhantomData, ops::RangeBounds};
 
 use bytemuck::{Pod, Zeroable};
 use wgpu::VertexBufferLayout;
+
+use crate::graphics::lowlevel::memory::TrackedAllocation;
+
 #[derive(Debug, Clone)]
 pub struct VertexBuffer<T>
 where
     T: VertexLayout,
 {
+    label: Option<String>,
     buffer: wgpu::Buffer,
+    _allocation: TrackedAllocation,
     _marker: PhantomData<T>,
 }
 
@@ -25,11 +30,25 @@ where
     /// The caller must ensure that the provided buffer is valid for the type T.
     pub unsafe fn from_raw_parts(buffer: wgpu::Buffer) -> Self {
         Self {
+            label: None,
+            _allocation: TrackedAllocation::buffer(&buffer),
             buffer,
             _marker: PhantomData,
         }
     }
 
+    /// Returns the label of the vertex buffer, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Sets the label of the vertex buffer.
+    ///
+    /// wgpu labels are fixed at creation, so this is only kept for debugging and doesn't rename the wgpu buffer.
+    pub fn set_label(&mut self, label: Option<&str>) {
+        self.label = label.map(|s| s.to_string());
+    }
+
     /// Returns the layout of the vertex buffer.
     pub fn layout(&self) -> VertexBufferLayout<'static> {
         T::LAYOUT
@@ -65,3 +84,44 @@ pub unsafe trait VertexLayout: Pod + Zeroable {
         );
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::{Pod, Zeroable};
+
+    use super::{VertexBuffer, VertexLayout};
+    use crate::graphics::lowlevel::test_device;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    struct Vertex([f32; 2]);
+
+    // Safety: a single vec2 attribute at offset 0, matching the struct.
+    unsafe impl VertexLayout for Vertex {
+        const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+            array_stride: 8,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+        };
+    }
+
+    #[test]
+    fn test_label_round_trip() {
+        let Some((device, _queue)) = test_device() else {
+            return;
+        };
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+
+        // Safety: the buffer holds a whole number of vertices.
+        let mut vertices = unsafe { VertexBuffer::<Vertex>::from_raw_parts(buffer) };
+        assert_eq!(vertices.label(), None);
+
+        vertices.set_label(Some("quad"));
+        assert_eq!(vertices.label(), Some("quad"));
+    }
+}
@@ -2,59 +2,84 @@ use wgpu::{CompareFunction, StoreOp, TextureFormat};
 
 use crate::{
     component::{ComponentHandle, ComponentStore},
-    graphics::lowlevel::WgpuRenderer,
+    graphics::lowlevel::{WgpuRenderer, fullscreen, memory::TrackedAllocation},
 };
 
+// copies must be aligned to `COPY_BYTES_PER_ROW_ALIGNMENT`, even for a single texel
+const READBACK_SIZE: u64 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64;
+
 /// A depth texture for use in rendering.
+///
+/// By default this is a depth-only `Depth32Float` texture. A combined depth-stencil texture can be created with
+/// `new_with_format`.
 #[derive(Clone, Debug)]
 pub struct DepthTexture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
+    // depth aspect only view, used for sampling. the same as `view` for depth-only formats.
+    depth_view: wgpu::TextureView,
+    format: TextureFormat,
+    sample_count: u32,
+    _allocation: TrackedAllocation,
     wgpu_handle: ComponentHandle<WgpuRenderer>,
 }
 
 impl DepthTexture {
-    /// The texture format used for the depth texture.
+    /// The texture format used for the depth texture by `new`.
     pub const TEXTURE_FORMAT: TextureFormat = TextureFormat::Depth32Float;
 
     /// Creates a new depth texture matching the current size of the swap chain.
     pub fn new(state: &ComponentStore) -> Self {
-        let wgpu = state.get::<WgpuRenderer>();
-        let config = wgpu.config.read().expect("CONFIG POISONED");
-        let size = wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
-            depth_or_array_layers: 1,
-        };
+        Self::new_with_format(state, Self::TEXTURE_FORMAT)
+    }
 
-        let desc = wgpu::TextureDescriptor {
-            label: Some("Depth Texture"),
-            size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: Self::TEXTURE_FORMAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        };
+    /// Creates a new depth texture with the given format, matching the current size of the swap chain.
+    ///
+    /// Formats with a stencil aspect (such as `Depth24PlusStencil8`) also clear and store the stencil in `attachment`.
+    ///
+    /// # Panics
+    /// Panics if `format` has no depth aspect.
+    pub fn new_with_format(state: &ComponentStore, format: TextureFormat) -> Self {
+        Self::new_with(state, format, 1)
+    }
 
-        let texture = wgpu.device.create_texture(&desc);
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    /// Creates a new multisampled depth texture matching the current size of the swap chain.
+    ///
+    /// `samples` must match the sample count of the color attachment it is used with. Multisampled depth textures
+    /// can't be sampled in shaders; they can only be read with `textureLoad` (`texture_depth_multisampled_2d`).
+    pub fn new_multisampled(state: &ComponentStore, samples: u32) -> Self {
+        Self::new_with(state, Self::TEXTURE_FORMAT, samples)
+    }
 
+    fn new_with(state: &ComponentStore, format: TextureFormat, sample_count: u32) -> Self {
+        assert!(
+            format.has_depth_aspect(),
+            "DepthTexture format {:?} has no depth aspect",
+            format
+        );
+
+        let wgpu = state.get::<WgpuRenderer>();
+        let (texture, view, depth_view) = Self::create(&wgpu, format, sample_count);
         let sampler = wgpu.comparing_sampler(CompareFunction::LessEqual);
 
         Self {
+            _allocation: TrackedAllocation::texture(&texture),
             texture,
             view,
             sampler,
+            depth_view,
+            format,
+            sample_count,
             wgpu_handle: state.handle_for(),
         }
     }
 
-    /// Resizes the depth texture to match the current size of the swap chain.
-    pub fn resize(&mut self) {
-        let wgpu = self.wgpu_handle.get();
+    fn create(
+        wgpu: &WgpuRenderer,
+        format: TextureFormat,
+        sample_count: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView, wgpu::TextureView) {
         let config = wgpu.config.read().expect("CONFIG POISONED");
         let size = wgpu::Extent3d {
             width: config.width,
@@ -66,26 +91,77 @@ impl DepthTexture {
             label: Some("Depth Texture"),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
-            format: Self::TEXTURE_FORMAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[],
         };
 
-        self.texture = wgpu.device.create_texture(&desc);
-        self.view = self
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let texture = wgpu.device.create_texture(&desc);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // textures with a stencil aspect can only be sampled through a single aspect
+        let depth_view = if format.has_stencil_aspect() {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Depth Texture depth view"),
+                aspect: wgpu::TextureAspect::DepthOnly,
+                ..Default::default()
+            })
+        } else {
+            view.clone()
+        };
+
+        (texture, view, depth_view)
+    }
+
+    /// Resizes the depth texture to match the current size of the swap chain.
+    pub fn resize(&mut self) {
+        let wgpu = self.wgpu_handle.get();
+        let (texture, view, depth_view) = Self::create(&wgpu, self.format, self.sample_count);
+        self._allocation = TrackedAllocation::texture(&texture);
+        self.texture = texture;
+        self.view = view;
+        self.depth_view = depth_view;
+    }
+
+    /// Returns the format of the depth texture.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Returns the number of samples per pixel. This is 1 unless created with `new_multisampled`.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Returns true if the depth texture has a stencil aspect.
+    pub fn has_stencil(&self) -> bool {
+        self.format.has_stencil_aspect()
     }
 
     /// Gets the depth stencil state for use in a render pipeline.
     pub fn state(&self) -> wgpu::DepthStencilState {
+        self.state_with_stencil(wgpu::StencilState::default())
+    }
+
+    /// Gets the depth stencil state for use in a render pipeline, using the given stencil state.
+    ///
+    /// # Panics
+    /// Panics if `stencil` is enabled but the depth texture has no stencil aspect.
+    pub fn state_with_stencil(&self, stencil: wgpu::StencilState) -> wgpu::DepthStencilState {
+        assert!(
+            !stencil.is_enabled() || self.has_stencil(),
+            "DepthTexture format {:?} has no stencil aspect",
+            self.format
+        );
+
         wgpu::DepthStencilState {
-            format: Self::TEXTURE_FORMAT,
+            format: self.format,
             depth_write_enabled: true,
             depth_compare: wgpu::CompareFunction::LessEqual,
-            stencil: wgpu::StencilState::default(),
+            stencil,
             bias: wgpu::DepthBiasState::default(),
         }
     }
@@ -98,11 +174,17 @@ impl DepthTexture {
                 load: wgpu::LoadOp::Clear(1.0),
                 store: StoreOp::Store,
             }),
-            stencil_ops: None,
+            stencil_ops: self.has_stencil().then_some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(0),
+                store: StoreOp::Store,
+            }),
         }
     }
 
     /// Creates a bind group layout entry for the depth texture.
+    ///
+    /// If the texture is multisampled, the layout declares a multisampled texture. It can then only be read with
+    /// `textureLoad`, and the sampler binding must go unused in the shader.
     pub fn bind_group_layout(
         &self,
         texture_binding: u32,
@@ -117,7 +199,7 @@ impl DepthTexture {
                     binding: texture_binding,
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
-                        multisampled: false,
+                        multisampled: self.sample_count > 1,
                         view_dimension: wgpu::TextureViewDimension::D2,
                         sample_type: wgpu::TextureSampleType::Depth,
                     },
@@ -134,6 +216,8 @@ impl DepthTexture {
     }
 
     /// Creates a bind group for the depth texture. Uses the given sampler.
+    ///
+    /// See `bind_group_layout` for the restrictions on multisampled textures.
     pub fn bind_group(
         &self,
         texture_binding: u32,
@@ -155,7 +239,7 @@ impl DepthTexture {
                 &[
                     wgpu::BindGroupEntry {
                         binding: texture_binding,
-                        resource: wgpu::BindingResource::TextureView(&self.view),
+                        resource: wgpu::BindingResource::TextureView(&self.depth_view),
                     },
                     wgpu::BindGroupEntry {
                         binding: sampler_binding,
@@ -165,4 +249,196 @@ impl DepthTexture {
             ),
         )
     }
+
+    /// Reads back the normalized depth at the given pixel.
+    ///
+    /// Formats that can be copied directly (`Depth32Float`, `Depth32FloatStencil8` and `Depth16Unorm`) are copied
+    /// into a staging buffer. Other formats, and multisampled textures, are first drawn into a 1x1 `R32Float`
+    /// texture, which builds a small pipeline each call.
+    ///
+    /// The returned future waits on the buffer mapping, so the device must be polled (see `WgpuRenderer::poll`)
+    /// for it to complete on native.
+    ///
+    /// # Panics
+    /// Panics if the pixel is out of bounds. The future panics if the buffer could not be mapped.
+    pub fn read_depth(&self, x: u32, y: u32) -> impl Future<Output = f32> + Send + 'static {
+        let size = self.texture.size();
+        assert!(
+            x < size.width && y < size.height,
+            "Depth read at ({x}, {y}) is outside of the {}x{} depth texture",
+            size.width,
+            size.height
+        );
+
+        let wgpu = self.wgpu_handle.get();
+        let buffer = wgpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("depth readback buffer"),
+            size: READBACK_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = wgpu.create_encoder(Some("depth readback encoder"));
+
+        let copyable = matches!(
+            self.format,
+            TextureFormat::Depth32Float
+                | TextureFormat::Depth32FloatStencil8
+                | TextureFormat::Depth16Unorm
+        );
+        let (source, origin, aspect, unorm16) = if copyable && self.sample_count == 1 {
+            (
+                self.texture.clone(),
+                wgpu::Origin3d { x, y, z: 0 },
+                wgpu::TextureAspect::DepthOnly,
+                self.format == TextureFormat::Depth16Unorm,
+            )
+        } else {
+            (
+                self.blit_depth_texel(&wgpu, &mut encoder, x, y),
+                wgpu::Origin3d::ZERO,
+                wgpu::TextureAspect::All,
+                false,
+            )
+        };
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &source,
+                mip_level: 0,
+                origin,
+                aspect,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(READBACK_SIZE as u32),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        wgpu.submit_single(encoder.finish());
+
+        let data = wgpu.read_buffer(&buffer);
+        async move {
+            let data = data.await.expect("Failed to map depth readback buffer");
+            if unorm16 {
+                u16::from_le_bytes([data[0], data[1]]) as f32 / u16::MAX as f32
+            } else {
+                f32::from_le_bytes([data[0], data[1], data[2], data[3]])
+            }
+        }
+    }
+
+    /// Draws the depth at the given pixel into a new 1x1 `R32Float` texture, for formats that can't be copied.
+    fn blit_depth_texel(
+        &self,
+        wgpu: &WgpuRenderer,
+        encoder: &mut wgpu::CommandEncoder,
+        x: u32,
+        y: u32,
+    ) -> wgpu::Texture {
+        let texture_type = if self.sample_count > 1 {
+            "texture_depth_multisampled_2d"
+        } else {
+            "texture_depth_2d"
+        };
+        let source = fullscreen::with_fragment(&format!(
+            r#"
+@group(0) @binding(0) var depth: {texture_type};
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {{
+    return vec4<f32>(textureLoad(depth, vec2<u32>({x}u, {y}u), 0), 0.0, 0.0, 1.0);
+}}
+"#
+        ));
+        let module = wgpu
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("depth readback shader"),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+
+        let layout = wgpu.bind_group_layout(
+            Some("depth readback bind group layout"),
+            &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: self.sample_count > 1,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Depth,
+                },
+                count: None,
+            }],
+        );
+        let bind_group = wgpu.bind_group(
+            Some("depth readback bind group"),
+            &layout,
+            &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&self.depth_view),
+            }],
+        );
+
+        let pipeline_layout = wgpu.pipeline_layout(Some("depth readback layout"), &[&layout]);
+        let pipeline = wgpu.create_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("depth readback pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: Some(fullscreen::VERTEX_ENTRY),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(TextureFormat::R32Float.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        let target = wgpu.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth readback texel"),
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TextureFormat::R32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        {
+            let mut pass = wgpu.render_pass(
+                Some("depth readback pass"),
+                encoder,
+                &view,
+                None,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            );
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            fullscreen::draw(&mut pass);
+        }
+
+        target
+    }
 }
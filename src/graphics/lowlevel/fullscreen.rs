@@ -0,0 +1,38 @@
+//! Full-screen triangle helper for post-process pipelines.
+//!
+//! A single oversized triangle covers the whole target without needing a vertex buffer. The UV convention matches
+//! wgpu texture coordinates: `(0, 0)` is the top-left corner of the target and `(1, 1)` is the bottom-right.
+
+/// The entry point of the vertex shader in [`VERTEX_SHADER`].
+pub const VERTEX_ENTRY: &str = "fullscreen_vs";
+
+/// WGSL vertex shader that emits a full-screen triangle from `vertex_index` alone.
+///
+/// The output struct `FullscreenVertexOutput` carries the clip-space position and the UV at `@location(0)`.
+pub const VERTEX_SHADER: &str = r#"
+struct FullscreenVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn fullscreen_vs(@builtin(vertex_index) vertex_index: u32) -> FullscreenVertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: FullscreenVertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+"#;
+
+/// Prepends [`VERTEX_SHADER`] to the given fragment shader source.
+///
+/// The fragment shader can take `FullscreenVertexOutput` (or `@location(0) uv: vec2<f32>`) as its input.
+pub fn with_fragment(fragment_source: &str) -> String {
+    format!("{VERTEX_SHADER}\n{fragment_source}")
+}
+
+/// Draws the full-screen triangle. The bound pipeline must use [`VERTEX_SHADER`] and have no vertex buffers.
+pub fn draw(pass: &mut wgpu::RenderPass<'_>) {
+    pass.draw(0..3, 0..1);
+}
@@ -0,0 +1,77 @@
+//! Tracking of GPU memory allocated through kiwi's buffer and texture wrappers.
+//!
+//! This only counts allocations made through the wrappers, not raw `wgpu` resources or driver overhead, so it is
+//! meant for spotting leaks rather than measuring real VRAM usage.
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the number of bytes currently held by tracked allocations.
+pub(crate) fn allocated_bytes() -> u64 {
+    ALLOCATED_BYTES.load(Ordering::Relaxed)
+}
+
+/// A tracked GPU allocation. The bytes are released once every clone has been dropped.
+///
+/// `wgpu` resources are reference counted, so wrappers that are `Clone` share one allocation instead of counting
+/// each clone separately.
+#[derive(Clone, Debug)]
+pub(crate) struct TrackedAllocation(#[allow(dead_code)] Arc<Allocation>);
+
+#[derive(Debug)]
+struct Allocation(u64);
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        ALLOCATED_BYTES.fetch_sub(self.0, Ordering::Relaxed);
+    }
+}
+
+impl TrackedAllocation {
+    /// Tracks an allocation of `bytes` bytes.
+    pub(crate) fn new(bytes: u64) -> Self {
+        ALLOCATED_BYTES.fetch_add(bytes, Ordering::Relaxed);
+        Self(Arc::new(Allocation(bytes)))
+    }
+
+    /// Tracks the size of `buffer`.
+    pub(crate) fn buffer(buffer: &wgpu::Buffer) -> Self {
+        Self::new(buffer.size())
+    }
+
+    /// Tracks the estimated size of `texture`, including all of its mip levels and samples.
+    pub(crate) fn texture(texture: &wgpu::Texture) -> Self {
+        let size = texture.size();
+        let format = texture.format();
+        let bytes = (0..texture.mip_level_count())
+            .map(|level| {
+                format.theoretical_memory_footprint(size.mip_level_size(level, texture.dimension()))
+            })
+            .sum::<u64>();
+        Self::new(bytes * texture.sample_count() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TrackedAllocation, allocated_bytes};
+
+    #[test]
+    fn test_allocation_returns_to_baseline() {
+        let baseline = allocated_bytes();
+
+        let allocation = TrackedAllocation::new(4096);
+        assert_eq!(allocated_bytes(), baseline + 4096);
+
+        // clones share the allocation, so dropping one of them releases nothing
+        let clone = allocation.clone();
+        drop(allocation);
+        assert_eq!(allocated_bytes(), baseline + 4096);
+
+        drop(clone);
+        assert_eq!(allocated_bytes(), baseline);
+    }
+}
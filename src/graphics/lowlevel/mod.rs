@@ -1,7 +1,7 @@
 //! Low-level graphics abstractions using WGPU.
 use std::{
     fmt::Debug,
-    sync::{Arc, OnceLock, RwLock},
+    sync::{Arc, Mutex, MutexGuard, OnceLock, RwLock},
 };
 
 use anyhow::Context;
@@ -12,22 +12,27 @@ use wgpu::{
     self as w, Color, CommandBuffer, CommandEncoder, CompareFunction, Device, DeviceDescriptor,
     Instance, InstanceDescriptor, Origin3d, PowerPreference, PresentMode, Queue, RenderPass,
     RequestAdapterOptions, StoreOp, Surface, SurfaceConfiguration, SurfaceTargetUnsafe,
-    SurfaceTexture, TextureAspect, TextureView, util::DeviceExt,
+    SurfaceTexture, TextureAspect, TextureView,
+    util::{DeviceExt, StagingBelt},
 };
-use winit::window::Window;
+use winit::{dpi::PhysicalSize, window::Window};
 
 use crate::{
     ReadOnly,
     component::{ComponentStore, ComponentStoreHandle},
     graphics::lowlevel::{
-        buf::{IndexBuffer, IndexLayout, UniformBuffer, VertexBuffer, VertexLayout},
-        shader::ShaderProgram,
+        buf::{IndexBuffer, IndexLayout, StorageBuffer, UniformBuffer, VertexBuffer, VertexLayout},
+        msaa::{MsaaTarget, UnsupportedSampleCount},
+        shader::{ShaderError, ShaderProgram},
         texture::Texture,
     },
 };
 
 pub mod buf;
 pub mod depth;
+pub mod fullscreen;
+pub(crate) mod memory;
+pub mod msaa;
 pub mod pipeline;
 pub mod shader;
 pub mod texture;
@@ -44,10 +49,59 @@ pub struct WgpuRenderer {
     pub queue: Queue,
     /// The surface configuration.
     pub config: RwLock<SurfaceConfiguration>,
+    msaa: RwLock<Option<MsaaTarget>>,
     default_sampler: OnceLock<wgpu::Sampler>,
+    staging_belt: Mutex<StagingBelt>,
     state: ComponentStoreHandle,
 }
 
+/// An error from acquiring the next swap chain texture with `WgpuRenderer::current_view`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum SurfaceError {
+    /// The surface was lost, e.g. because the window was recreated. Reconfigure the surface and retry.
+    #[error("the surface was lost")]
+    Lost,
+    /// The surface no longer matches the window, e.g. after a resize. Reconfigure the surface and retry.
+    #[error("the surface is outdated")]
+    Outdated,
+    /// The texture wasn't acquired in time. Skip the frame.
+    #[error("timed out acquiring the next swap chain texture")]
+    Timeout,
+    /// There is no memory left to allocate the texture. This is fatal.
+    #[error("out of memory acquiring the next swap chain texture")]
+    OutOfMemory,
+    /// Any other failure reported by wgpu.
+    #[error("failed to acquire the next swap chain texture")]
+    Other,
+}
+
+impl SurfaceError {
+    /// Returns true if the surface should be reconfigured (e.g. with `WgpuRenderer::resize`) before retrying.
+    pub fn needs_reconfigure(&self) -> bool {
+        matches!(self, Self::Lost | Self::Outdated)
+    }
+
+    /// Returns true if rendering can't continue.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::OutOfMemory)
+    }
+}
+
+impl From<wgpu::SurfaceError> for SurfaceError {
+    fn from(err: wgpu::SurfaceError) -> Self {
+        match err {
+            wgpu::SurfaceError::Lost => Self::Lost,
+            wgpu::SurfaceError::Outdated => Self::Outdated,
+            wgpu::SurfaceError::Timeout => Self::Timeout,
+            wgpu::SurfaceError::OutOfMemory => Self::OutOfMemory,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The size of each chunk allocated by the staging belt.
+const STAGING_CHUNK_SIZE: u64 = 1 << 16;
+
 impl WgpuRenderer {
     /// Attaches a WGPU renderer to the given state and window.
     ///
@@ -76,10 +130,15 @@ impl WgpuRenderer {
             .await
             .with_context(|| "Failed to find an appropriate adapter")?;
 
+        // timestamp queries are optional, and only used for profiling
+        let timing_features =
+            wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS;
+
         let (device, queue) = adapter
             .request_device(&DeviceDescriptor {
                 label: Some("root device"),
-                required_features: wgpu::Features::POLYGON_MODE_LINE,
+                required_features: wgpu::Features::POLYGON_MODE_LINE
+                    | (adapter.features() & timing_features),
                 ..Default::default()
             })
             .await
@@ -107,30 +166,85 @@ impl WgpuRenderer {
 
         surface.configure(&device, &config);
 
+        let staging_belt = StagingBelt::new(device.clone(), STAGING_CHUNK_SIZE);
+
         let this = WgpuRenderer {
             instance,
             surface,
             device,
             queue,
             config: RwLock::new(config),
+            msaa: RwLock::new(None),
             state: state.clone(),
             default_sampler: OnceLock::new(),
+            staging_belt: Mutex::new(staging_belt),
         };
 
         state.insert(this);
         Ok(())
     }
 
-    /// Resize the surface to the new size.
+    /// Resizes the surface to the new size, updating `config` and reconfiguring the surface in one step.
     ///
-    /// # Panics
-    /// Panics if the new size has a width or height less than or equal to zero.
-    pub fn resize(&self, new_size: (i32, i32)) {
-        debug_assert!(new_size.0 > 0 && new_size.1 > 0, "Window size <= 0");
+    /// A zero width or height (e.g. a minimized window) is raised to 1, as the surface can't be configured with it.
+    ///
+    /// The MSAA target, if enabled, is recreated at the new size.
+    ///
+    /// Returns the new width and height, so dependents such as `DepthTexture::resize` and `Camera::resize` can be
+    /// updated to match.
+    pub fn resize(&self, new_size: PhysicalSize<u32>) -> (u32, u32) {
         let mut cfg = self.config.write().expect("CONFIG POISONED");
-        cfg.width = new_size.0 as u32;
-        cfg.height = new_size.1 as u32;
+        cfg.width = new_size.width.max(1);
+        cfg.height = new_size.height.max(1);
         self.surface.configure(&self.device, &cfg);
+
+        let mut msaa = self.msaa.write().expect("MSAA POISONED");
+        if let Some(target) = msaa.as_mut() {
+            *target = MsaaTarget::new(&self.device, &cfg, target.sample_count());
+        }
+        (cfg.width, cfg.height)
+    }
+
+    /// Sets the number of samples per pixel used when rendering to the swap chain. `1` disables multisampling.
+    ///
+    /// While enabled, `RenderController` renders every pipeline into a multisampled `MsaaTarget` and resolves it
+    /// into the swap chain at the end of the frame. Pipelines built with `pipeline_builder` afterwards use the same
+    /// sample count, so pipelines built before this call must be rebuilt. Pipelines built by hand should use
+    /// `sample_count` for their `MultisampleState`, and their depth texture should be created with
+    /// `DepthTexture::new_multisampled`.
+    ///
+    /// The sample count must be supported by the surface format (4 is supported everywhere). If it isn't, an error
+    /// is returned and the current MSAA target is left as is.
+    pub fn set_msaa(&self, sample_count: u32) -> Result<(), UnsupportedSampleCount> {
+        // locked in the same order as `resize`
+        let cfg = self.config.read().expect("CONFIG POISONED");
+        MsaaTarget::check_sample_count(&self.device, cfg.format, sample_count)?;
+        let mut msaa = self.msaa.write().expect("MSAA POISONED");
+        *msaa = (sample_count > 1).then(|| MsaaTarget::new(&self.device, &cfg, sample_count));
+        Ok(())
+    }
+
+    /// Returns the number of samples per pixel used when rendering to the swap chain. This is 1 unless set with
+    /// `set_msaa`.
+    pub fn sample_count(&self) -> u32 {
+        self.msaa
+            .read()
+            .expect("MSAA POISONED")
+            .as_ref()
+            .map_or(1, MsaaTarget::sample_count)
+    }
+
+    /// Returns the MSAA target, if multisampling is enabled with `set_msaa`.
+    pub fn msaa_target(&self) -> Option<MsaaTarget> {
+        self.msaa.read().expect("MSAA POISONED").clone()
+    }
+
+    /// Returns the number of bytes of GPU memory held by kiwi's buffer and texture wrappers.
+    ///
+    /// This is counted across all renderers, and doesn't include resources created directly through `device`, or
+    /// driver overhead. It is intended for catching leaks, not as a measure of real memory usage.
+    pub fn memory_usage(&self) -> u64 {
+        memory::allocated_bytes()
     }
 
     /// Sets the default sampler. This can only be set once.
@@ -163,7 +277,9 @@ impl WgpuRenderer {
             });
 
         // Safety: The buffer is valid for type T as it was created from a slice of T.
-        unsafe { VertexBuffer::from_raw_parts(buffer) }
+        let mut vertex_buffer = unsafe { VertexBuffer::from_raw_parts(buffer) };
+        vertex_buffer.set_label(label);
+        vertex_buffer
     }
 
     /// Creates an index buffer with the given usage and data.
@@ -200,7 +316,31 @@ impl WgpuRenderer {
         unsafe { UniformBuffer::from_raw_parts(label, buffer, self.state.clone()) }
     }
 
+    /// Creates a storage buffer with the given data.
+    ///
+    /// The buffer can also be copied from, so it can be read back or used as the source of other buffers.
+    pub fn storage_buffer<T>(&self, data: &[T], label: Option<&str>) -> StorageBuffer<T>
+    where
+        T: Pod,
+    {
+        let buffer = self
+            .device
+            .create_buffer_init(&w::util::BufferInitDescriptor {
+                label,
+                contents: bytemuck::cast_slice(data),
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+            });
+
+        // Safety: The buffer is valid for type T as it was created from a slice of T.
+        unsafe { StorageBuffer::from_raw_parts(label, buffer, self.state.clone()) }
+    }
+
     /// Loads a shader module from WGSL source code.
+    ///
+    /// # Panics
+    /// Panics if an `//!include` directive in the source cannot be resolved.
     pub fn load_shader(
         &self,
         shader_source: &str,
@@ -208,6 +348,8 @@ impl WgpuRenderer {
         vs_entry: Option<&str>,
         fs_entry: Option<&str>,
     ) -> ShaderProgram {
+        let shader_source = shader::preprocess(shader_source)
+            .unwrap_or_else(|err| panic!("Failed to preprocess shader {label:?}: {err}"));
         let module = self
             .device
             .create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -215,7 +357,32 @@ impl WgpuRenderer {
                 source: wgpu::ShaderSource::Wgsl(shader_source.into()),
             });
 
-        ShaderProgram::from_raw_parts(module, vs_entry.map(Arc::from), fs_entry.map(Arc::from))
+        ShaderProgram::from_raw_parts(
+            self.device.clone(),
+            module,
+            label.map(Arc::from),
+            vs_entry.map(Arc::from),
+            fs_entry.map(Arc::from),
+        )
+    }
+
+    /// Loads a shader module from WGSL source code, returning the validation error if it fails to compile.
+    pub fn try_load_shader(
+        &self,
+        shader_source: &str,
+        label: Option<&str>,
+        vs_entry: Option<&str>,
+        fs_entry: Option<&str>,
+    ) -> Result<ShaderProgram, ShaderError> {
+        let module = shader::compile(&self.device, label, shader_source)?;
+
+        Ok(ShaderProgram::from_raw_parts(
+            self.device.clone(),
+            module,
+            label.map(Arc::from),
+            vs_entry.map(Arc::from),
+            fs_entry.map(Arc::from),
+        ))
     }
 
     /// Creates a texture with the given descriptor.
@@ -492,11 +659,10 @@ impl WgpuRenderer {
     }
 
     /// Acquires the current texture view from the surface.
-    pub fn current_view(&self) -> anyhow::Result<(SurfaceTexture, TextureView)> {
-        let frame = self
-            .surface
-            .get_current_texture()
-            .with_context(|| "Failed to acquire next swap chain texture")?;
+    ///
+    /// On failure, the `SurfaceError` tells whether to reconfigure the surface, skip the frame, or give up.
+    pub fn current_view(&self) -> Result<(SurfaceTexture, TextureView), SurfaceError> {
+        let frame = self.surface.get_current_texture()?;
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -538,6 +704,80 @@ impl WgpuRenderer {
         self.queue.submit(bufs);
     }
 
+    /// Returns the staging belt used to batch many small buffer writes into a few mapped chunks.
+    ///
+    /// Writes through the belt are recorded into a command encoder. Call `finish_staging` after the last write
+    /// and before finishing the encoder, then `recall_staging` once that encoder has been submitted.
+    pub fn staging_belt(&self) -> MutexGuard<'_, StagingBelt> {
+        self.staging_belt.lock().expect("STAGING BELT POISONED")
+    }
+
+    /// Writes `data` to `target` at `offset` through the staging belt.
+    ///
+    /// # Panics
+    /// Panics if `data` is empty.
+    pub fn write_buffer_staged(
+        &self,
+        encoder: &mut CommandEncoder,
+        target: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        let size = wgpu::BufferSize::new(data.len() as u64).expect("Staged write of 0 bytes");
+        self.staging_belt()
+            .write_buffer(encoder, target, offset, size)
+            .copy_from_slice(data);
+    }
+
+    /// Closes the staging belt's chunks written this frame. Call this before finishing the encoder they were
+    /// written to.
+    pub fn finish_staging(&self) {
+        self.staging_belt().finish();
+    }
+
+    /// Recalls the staging belt's chunks so they can be reused. Call this after submitting the encoder they were
+    /// written to, usually once per frame.
+    ///
+    /// This isn't done by `submit`, as other submits (such as buffer readbacks) can happen while the frame's encoder
+    /// is still being recorded, and its chunks must not be reused before it is submitted.
+    pub fn recall_staging(&self) {
+        self.staging_belt().recall();
+    }
+
+    /// Polls the device, running any pending `map_async` callbacks. This is a direct wrapper around `Device::poll`.
+    ///
+    /// Buffer readbacks (such as `read_buffer`) only make progress when the device is polled. On the web this is
+    /// done by the browser, so this is a no-op there.
+    pub fn poll(&self, poll_type: wgpu::PollType) -> Result<wgpu::PollStatus, wgpu::PollError> {
+        self.device.poll(poll_type)
+    }
+
+    /// Maps the entirety of `buffer` for reading and returns its contents once the mapping completes.
+    ///
+    /// The buffer must have been created with `MAP_READ` and all writes to it must have been submitted.
+    /// The returned future does not drive the device itself; the caller must call `poll` (or otherwise poll the
+    /// device) for it to complete on native.
+    pub fn read_buffer(
+        &self,
+        buffer: &wgpu::Buffer,
+    ) -> impl Future<Output = Result<Vec<u8>, wgpu::BufferAsyncError>> + Send + 'static {
+        let buffer = buffer.clone();
+        let (tx, rx) = smol::channel::bounded(1);
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = tx.try_send(result);
+            });
+
+        async move {
+            // the callback is dropped without being called if the device is lost
+            rx.recv().await.unwrap_or(Err(wgpu::BufferAsyncError))?;
+            let data = buffer.slice(..).get_mapped_range().to_vec();
+            buffer.unmap();
+            Ok(data)
+        }
+    }
+
     /// Returns the current dimensions of the surface.
     pub fn dimensions(&self) -> (u32, u32) {
         let cfg = self.config.read().expect("CONFIG POISONED");
@@ -550,3 +790,12 @@ impl Debug for WgpuRenderer {
         f.debug_struct("WgpuRenderer").finish_non_exhaustive()
     }
 }
+
+/// Creates a headless device for tests, or returns None if the machine has no usable adapter.
+#[cfg(test)]
+pub(crate) fn test_device() -> Option<(Device, Queue)> {
+    let instance = Instance::new(&InstanceDescriptor::default());
+    let adapter =
+        smol::block_on(instance.request_adapter(&RequestAdapterOptions::default())).ok()?;
+    smol::block_on(adapter.request_device(&DeviceDescriptor::default())).ok()
+}
@@ -0,0 +1,125 @@
+use wgpu::{StoreOp, SurfaceConfiguration, TextureFormat};
+
+use crate::graphics::lowlevel::memory::TrackedAllocation;
+
+/// Returned by `WgpuRenderer::set_msaa` when the surface format can't be multisampled with the requested count.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{format:?} does not support {sample_count} samples per pixel (supported: {supported:?})")]
+pub struct UnsupportedSampleCount {
+    /// The requested sample count.
+    pub sample_count: u32,
+    /// The format that was checked.
+    pub format: TextureFormat,
+    /// The sample counts the format supports on this device.
+    pub supported: Vec<u32>,
+}
+
+/// A multisampled color texture matching the swap chain, which is resolved into the swap chain at the end of a frame.
+///
+/// This is owned by `WgpuRenderer` and created with `WgpuRenderer::set_msaa`. `RenderController` renders every
+/// pipeline into it while it is enabled, then resolves it with `resolve`.
+#[derive(Clone, Debug)]
+pub struct MsaaTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    sample_count: u32,
+    _allocation: TrackedAllocation,
+}
+
+impl MsaaTarget {
+    /// Creates a multisampled color texture with the format and size of the given surface configuration.
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        config: &SurfaceConfiguration,
+        sample_count: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Color Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            _allocation: TrackedAllocation::texture(&texture),
+            texture,
+            view,
+            sample_count,
+        }
+    }
+
+    /// Checks that `format` can be rendered to with `sample_count` samples per pixel on `device`.
+    ///
+    /// Only the guaranteed format features are used, as kiwi doesn't enable
+    /// `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`.
+    pub fn check_sample_count(
+        device: &wgpu::Device,
+        format: TextureFormat,
+        sample_count: u32,
+    ) -> Result<(), UnsupportedSampleCount> {
+        let flags = format.guaranteed_format_features(device.features()).flags;
+        if sample_count == 1 || flags.sample_count_supported(sample_count) {
+            return Ok(());
+        }
+        Err(UnsupportedSampleCount {
+            sample_count,
+            format,
+            supported: flags.supported_sample_counts(),
+        })
+    }
+
+    /// Returns the number of samples per pixel.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Resolves the multisampled texture into `target`, which must be single sampled and share its format and size.
+    ///
+    /// This records an empty render pass that only resolves, so it can run after every pipeline has rendered.
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("MSAA resolve pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: Some(target),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    // the samples aren't needed once resolved
+                    store: StoreOp::Discard,
+                },
+                depth_slice: None,
+            })],
+            ..Default::default()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::lowlevel::test_device;
+
+    #[test]
+    fn test_check_sample_count() {
+        let Some((device, _queue)) = test_device() else {
+            return;
+        };
+        let format = TextureFormat::Bgra8UnormSrgb;
+        assert!(MsaaTarget::check_sample_count(&device, format, 1).is_ok());
+        assert!(MsaaTarget::check_sample_count(&device, format, 4).is_ok());
+
+        let err = MsaaTarget::check_sample_count(&device, format, 3).unwrap_err();
+        assert_eq!(err.sample_count, 3);
+        assert!(err.supported.contains(&4));
+        assert!(!err.supported.contains(&3));
+    }
+}
@@ -1,6 +1,8 @@
 use wgpu::{BindGroupLayout, VertexBufferLayout};
 
-use crate::graphics::lowlevel::{WgpuRenderer, buf::VertexLayout, shader::ShaderProgram};
+use crate::graphics::lowlevel::{
+    WgpuRenderer, buf::VertexLayout, depth::DepthTexture, shader::ShaderProgram,
+};
 
 /// A builder for creating render pipelines.
 #[derive(Debug)]
@@ -12,7 +14,9 @@ pub struct PipelineBuilder<'a> {
     layouts: Vec<VertexBufferLayout<'static>>,
     primitive_state: wgpu::PrimitiveState,
     color_targets: Vec<Option<wgpu::ColorTargetState>>,
+    blend: Option<wgpu::BlendState>,
     depth_stencil: Option<wgpu::DepthStencilState>,
+    sample_count: Option<u32>,
 }
 
 impl<'a> PipelineBuilder<'a> {
@@ -26,7 +30,9 @@ impl<'a> PipelineBuilder<'a> {
             shader_module: None,
             primitive_state: wgpu::PrimitiveState::default(),
             color_targets: Vec::new(),
+            blend: None,
             depth_stencil: None,
+            sample_count: None,
         }
     }
 
@@ -61,7 +67,7 @@ impl<'a> PipelineBuilder<'a> {
     pub fn add_color_target(mut self, format: wgpu::TextureFormat) -> Self {
         self.color_targets.push(Some(wgpu::ColorTargetState {
             format,
-            blend: Some(wgpu::BlendState::REPLACE),
+            blend: None,
             write_mask: wgpu::ColorWrites::ALL,
         }));
         self
@@ -78,6 +84,39 @@ impl<'a> PipelineBuilder<'a> {
         self.add_color_target(format)
     }
 
+    /// Sets the blend state used by every color target of the pipeline.
+    pub fn with_blend(mut self, blend: wgpu::BlendState) -> Self {
+        self.blend = Some(blend);
+        self
+    }
+
+    /// Blends color targets using standard alpha blending (`src * src_alpha + dst * (1 - src_alpha)`).
+    pub fn with_alpha_blending(self) -> Self {
+        self.with_blend(wgpu::BlendState::ALPHA_BLENDING)
+    }
+
+    /// Blends color targets additively, weighting the source by its alpha. Useful for particles and glows.
+    pub fn with_additive_blending(self) -> Self {
+        self.with_blend(wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        })
+    }
+
+    /// Disables blending, so fragments replace whatever is in the color targets. This is the default.
+    pub fn with_no_blending(mut self) -> Self {
+        self.blend = None;
+        self
+    }
+
     /// Sets the primitive state for the pipeline.
     pub fn primitive_state(mut self, state: wgpu::PrimitiveState) -> Self {
         self.primitive_state = state;
@@ -90,12 +129,60 @@ impl<'a> PipelineBuilder<'a> {
         self
     }
 
+    /// Enables depth testing with `compare`, writing depth only if `write_enabled` is set.
+    ///
+    /// This keeps the format, stencil and bias of a state set with [`PipelineBuilder::depth`], so it can be used to
+    /// override the defaults of [`DepthTexture::state`]. Otherwise the state uses [`DepthTexture::TEXTURE_FORMAT`].
+    ///
+    /// Transparent passes usually want `with_depth(CompareFunction::LessEqual, false)`, so they are hidden by opaque
+    /// geometry without hiding each other.
+    pub fn with_depth(mut self, compare: wgpu::CompareFunction, write_enabled: bool) -> Self {
+        let state = self
+            .depth_stencil
+            .get_or_insert_with(|| wgpu::DepthStencilState {
+                format: DepthTexture::TEXTURE_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            });
+        state.depth_compare = compare;
+        state.depth_write_enabled = write_enabled;
+        self
+    }
+
+    /// Disables depth testing and writing entirely. The pipeline can then be used in passes without a depth
+    /// attachment, e.g. for UI.
+    pub fn without_depth(mut self) -> Self {
+        self.depth_stencil = None;
+        self
+    }
+
+    /// Sets the number of samples per pixel of the render target.
+    ///
+    /// Defaults to `WgpuRenderer::sample_count` at build time, which matches the target pipelines are given by
+    /// `RenderController`. Pipelines that render into their own single sampled textures should set this to 1.
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = Some(sample_count);
+        self
+    }
+
     /// Builds the pipeline.
     pub fn build(
         self,
         compilation_options: Option<wgpu::PipelineCompilationOptions<'_>>,
     ) -> WgpuPipeline {
         let shader = self.shader_module.expect("Shader module must be set");
+        let color_targets = self
+            .color_targets
+            .into_iter()
+            .map(|target| {
+                target.map(|target| wgpu::ColorTargetState {
+                    blend: self.blend,
+                    ..target
+                })
+            })
+            .collect::<Vec<_>>();
 
         let pipeline_layout =
             self.wgpu
@@ -113,10 +200,15 @@ impl<'a> PipelineBuilder<'a> {
                 label: Some(self.label),
                 layout: Some(&pipeline_layout),
                 vertex: shader.vertex_state(&self.layouts, compilation_options.clone()),
-                fragment: shader.fragment_state(&self.color_targets, compilation_options),
+                fragment: shader.fragment_state(&color_targets, compilation_options),
                 primitive: self.primitive_state,
                 depth_stencil: self.depth_stencil,
-                multisample: wgpu::MultisampleState::default(),
+                multisample: wgpu::MultisampleState {
+                    count: self
+                        .sample_count
+                        .unwrap_or_else(|| self.wgpu.sample_count()),
+                    ..Default::default()
+                },
                 multiview_mask: None,
                 cache: None,
             });
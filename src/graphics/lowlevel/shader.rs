@@ -1,13 +1,129 @@
 //! wgpu shader abstractions
 
+use std::{collections::HashMap, sync::LazyLock};
+
+use parking_lot::RwLock;
+use thiserror::Error;
 use wgpu::VertexBufferLayout;
 
 use crate::ReadOnlyString;
 
+/// The directive that splices a registered snippet into a shader, e.g. `//!include "lighting"`.
+const INCLUDE_DIRECTIVE: &str = "//!include";
+
+static INCLUDES: LazyLock<RwLock<HashMap<ReadOnlyString, ReadOnlyString>>> =
+    LazyLock::new(Default::default);
+
+/// An error that occurred while compiling a shader.
+#[derive(Debug, Clone, Error)]
+pub enum ShaderError {
+    /// The shader failed validation. Contains the error reported by wgpu.
+    #[error("shader compilation failed: {0}")]
+    Compilation(String),
+    /// An `//!include` directive named a snippet that was never registered.
+    #[error("unknown include \"{name}\" (included from {})", .chain.join(" -> "))]
+    UnknownInclude {
+        name: ReadOnlyString,
+        chain: Vec<ReadOnlyString>,
+    },
+    /// A snippet includes itself, directly or through other snippets. The chain ends with the repeated snippet.
+    #[error("include cycle: {}", .chain.join(" -> "))]
+    IncludeCycle { chain: Vec<ReadOnlyString> },
+    /// An `//!include` directive is not followed by a quoted name.
+    #[error("malformed include directive: {0}")]
+    MalformedInclude(String),
+}
+
+/// Resolves `//!include "name"` directives in `source` using the snippets registered with
+/// [`ShaderProgram::register_include`].
+///
+/// Each snippet is only spliced in once per shader, so shared dependencies of several snippets don't get defined
+/// twice.
+pub fn preprocess(source: &str) -> Result<String, ShaderError> {
+    let includes = INCLUDES.read();
+    let mut output = String::with_capacity(source.len());
+    let mut chain = vec![ReadOnlyString::from("<root>")];
+    let mut included = Vec::new();
+    splice(
+        source,
+        &|name| includes.get(name).cloned(),
+        &mut chain,
+        &mut included,
+        &mut output,
+    )?;
+    Ok(output)
+}
+
+fn splice(
+    source: &str,
+    lookup: &dyn Fn(&str) -> Option<ReadOnlyString>,
+    chain: &mut Vec<ReadOnlyString>,
+    included: &mut Vec<ReadOnlyString>,
+    output: &mut String,
+) -> Result<(), ShaderError> {
+    for line in source.lines() {
+        let Some(rest) = line.trim().strip_prefix(INCLUDE_DIRECTIVE) else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+
+        let name = rest
+            .trim()
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+            .ok_or_else(|| ShaderError::MalformedInclude(line.trim().to_string()))?;
+
+        if chain.iter().any(|entry| &**entry == name) {
+            let mut chain = chain[1..].to_vec();
+            chain.push(name.into());
+            return Err(ShaderError::IncludeCycle { chain });
+        }
+        if included.iter().any(|entry| &**entry == name) {
+            continue;
+        }
+
+        let snippet = lookup(name).ok_or_else(|| ShaderError::UnknownInclude {
+            name: name.into(),
+            chain: chain.clone(),
+        })?;
+
+        chain.push(name.into());
+        splice(&snippet, lookup, chain, included, output)?;
+        chain.pop();
+        included.push(name.into());
+    }
+    Ok(())
+}
+
+/// Preprocesses and compiles `source` into a shader module, capturing validation errors instead of letting them reach the device's
+/// uncaptured error handler.
+pub(crate) fn compile(
+    device: &wgpu::Device,
+    label: Option<&str>,
+    source: &str,
+) -> Result<wgpu::ShaderModule, ShaderError> {
+    let source = preprocess(source)?;
+    let scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label,
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+
+    match smol::block_on(scope.pop()) {
+        Some(err) => Err(ShaderError::Compilation(err.to_string())),
+        None => Ok(module),
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ShaderProgram {
     /// The shader module containing the shader code.
     pub module: wgpu::ShaderModule,
+    /// The device the module was created on, kept so the shader can be recompiled.
+    device: wgpu::Device,
+    /// The label of the shader module.
+    label: Option<ReadOnlyString>,
     /// The entry point for the vertex shader.
     pub vertex_entry_point: Option<ReadOnlyString>,
     /// The entry point for the fragment shader.
@@ -19,17 +135,39 @@ impl ShaderProgram {
     ///
     /// You probably want to use [`crate::graphics::WgpuInstance::load_shader`] to create the shader module.
     pub fn from_raw_parts(
+        device: wgpu::Device,
         module: wgpu::ShaderModule,
+        label: Option<ReadOnlyString>,
         vertex_entry_point: Option<ReadOnlyString>,
         fragment_entry_point: Option<ReadOnlyString>,
     ) -> Self {
         Self {
             module,
+            device,
+            label,
             vertex_entry_point,
             fragment_entry_point,
         }
     }
 
+    /// Recompiles the shader from `new_source`, replacing the module in place.
+    ///
+    /// Pipelines already built from this shader keep using the old module until they are rebuilt. If the new source
+    /// fails to compile, the old module is kept and the validation error is returned.
+    pub fn reload(&mut self, new_source: &str) -> Result<(), ShaderError> {
+        self.module = compile(&self.device, self.label.as_deref(), new_source)?;
+        Ok(())
+    }
+
+    /// Registers a snippet that shaders can splice in with `//!include "name"`, replacing any snippet previously
+    /// registered under `name`.
+    ///
+    /// Snippets are global and only resolved when a shader is compiled, so they should be registered before the
+    /// shaders using them are loaded.
+    pub fn register_include(name: impl Into<ReadOnlyString>, source: impl Into<ReadOnlyString>) {
+        INCLUDES.write().insert(name.into(), source.into());
+    }
+
     /// Returns the vertex state for this shader program.
     pub fn vertex_state<'a>(
         &'a self,
@@ -60,3 +198,43 @@ impl ShaderProgram {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ShaderError, ShaderProgram, preprocess};
+
+    #[test]
+    fn test_preprocess_splices_includes_once() {
+        ShaderProgram::register_include("test_math", "fn square(x: f32) -> f32 { return x * x; }");
+        ShaderProgram::register_include("test_lighting", "//!include \"test_math\"\nfn light() {}");
+
+        let source = "//!include \"test_lighting\"\n  //!include \"test_math\"\nfn main() {}";
+        let output = preprocess(source).unwrap();
+        assert_eq!(
+            output,
+            "fn square(x: f32) -> f32 { return x * x; }\nfn light() {}\nfn main() {}\n"
+        );
+    }
+
+    #[test]
+    fn test_preprocess_reports_include_chain() {
+        ShaderProgram::register_include("test_cycle_a", "//!include \"test_cycle_b\"");
+        ShaderProgram::register_include("test_cycle_b", "//!include \"test_cycle_a\"");
+
+        let Err(ShaderError::IncludeCycle { chain }) = preprocess("//!include \"test_cycle_a\"")
+        else {
+            panic!("expected an include cycle");
+        };
+        let chain = chain.iter().map(|name| &**name).collect::<Vec<_>>();
+        assert_eq!(chain, ["test_cycle_a", "test_cycle_b", "test_cycle_a"]);
+
+        ShaderProgram::register_include("test_missing_parent", "//!include \"test_missing\"");
+        let Err(ShaderError::UnknownInclude { name, chain }) =
+            preprocess("//!include \"test_missing_parent\"")
+        else {
+            panic!("expected an unknown include");
+        };
+        assert_eq!(&*name, "test_missing");
+        assert_eq!(chain.len(), 2);
+    }
+}
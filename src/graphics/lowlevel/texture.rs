@@ -1,8 +1,13 @@
 use std::fmt::Debug;
 
+use image::ImageResult;
+
 use crate::{
     component::{ComponentHandle, ComponentStoreHandle},
-    graphics::lowlevel::WgpuRenderer,
+    graphics::{
+        image::Image,
+        lowlevel::{WgpuRenderer, memory::TrackedAllocation},
+    },
 };
 
 /// A structure representing a texture, its view, and its sampler.
@@ -19,6 +24,7 @@ pub struct Texture {
     /// The texture view.
     pub view: wgpu::TextureView,
     image_count: usize,
+    _allocation: TrackedAllocation,
     handle: ComponentHandle<WgpuRenderer>,
 }
 
@@ -34,6 +40,7 @@ impl Texture {
         image_count: usize,
     ) -> Self {
         Self {
+            _allocation: TrackedAllocation::texture(&texture),
             texture,
             texture_bind_group_entry,
             sampler,
@@ -44,6 +51,51 @@ impl Texture {
         }
     }
 
+    /// Decodes an image (PNG, JPEG, or any other format supported by `image`) and uploads it to a new texture.
+    ///
+    /// `format` selects how the texture is sampled: `Rgba8UnormSrgb` for color images, or `Rgba8Unorm` for linear
+    /// data such as normal maps. The renderer's default sampler is used if set, otherwise a linear one is created.
+    ///
+    /// Like other `Texture`s, the result is a single-layer array, so shaders sample it as a `texture_2d_array`.
+    ///
+    /// # Panics
+    /// Panics if `format` is not `Rgba8Unorm` or `Rgba8UnormSrgb`.
+    pub fn from_bytes(
+        state: &ComponentStoreHandle,
+        bytes: &[u8],
+        format: wgpu::TextureFormat,
+    ) -> ImageResult<Self> {
+        assert!(
+            matches!(
+                format,
+                wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb
+            ),
+            "Texture::from_bytes only supports Rgba8Unorm and Rgba8UnormSrgb, got {:?}",
+            format
+        );
+
+        let image = Image::from_mem(bytes)?;
+        let wgpu = state.get::<WgpuRenderer>();
+        let sampler = wgpu.default_sampler().cloned().unwrap_or_else(|| {
+            wgpu.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Texture Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            })
+        });
+
+        Ok(wgpu.texture(
+            Some("Texture"),
+            &sampler,
+            format,
+            32,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            image.dimensions(),
+            &[image.pixel_bytes().clone()],
+        ))
+    }
+
     /// Creates a bind group layout for this texture.
     pub fn layout(
         &self,
@@ -0,0 +1,76 @@
+use std::ops::Range;
+
+use crate::{
+    component::ComponentStore,
+    graphics::lowlevel::{
+        WgpuRenderer,
+        buf::{IndexBuffer, IndexLayout, InstanceBuffer, VertexBuffer, VertexLayout},
+    },
+};
+
+/// An indexed mesh: a vertex buffer and an index buffer drawn together.
+#[derive(Debug, Clone)]
+pub struct Mesh<V, I = u32>
+where
+    V: VertexLayout,
+    I: IndexLayout,
+{
+    vertices: VertexBuffer<V>,
+    indices: IndexBuffer<I>,
+}
+
+impl<V: VertexLayout, I: IndexLayout> Mesh<V, I> {
+    /// Creates a mesh by uploading `vertices` and `indices` to new buffers.
+    pub fn new(state: &ComponentStore, vertices: &[V], indices: &[I]) -> Self {
+        let wgpu = state.get::<WgpuRenderer>();
+        Self {
+            vertices: wgpu.vertex_buffer(vertices, Some("Mesh Vertices")),
+            indices: wgpu.index_buffer(indices, Some("Mesh Indices")),
+        }
+    }
+
+    /// Creates a mesh from existing buffers.
+    pub fn from_buffers(vertices: VertexBuffer<V>, indices: IndexBuffer<I>) -> Self {
+        Self { vertices, indices }
+    }
+
+    /// Returns the vertex buffer.
+    pub fn vertex_buffer(&self) -> &VertexBuffer<V> {
+        &self.vertices
+    }
+
+    /// Returns the index buffer.
+    pub fn index_buffer(&self) -> &IndexBuffer<I> {
+        &self.indices
+    }
+
+    /// Returns the number of indices drawn by `draw`.
+    pub fn index_count(&self) -> u32 {
+        self.indices.count() as u32
+    }
+
+    /// Binds the mesh's buffers (the vertex buffer at slot 0) and draws it once.
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>) {
+        self.draw_instanced(pass, 0..1);
+    }
+
+    /// Binds the mesh's buffers (the vertex buffer at slot 0) and draws the given range of instances.
+    pub fn draw_instanced(&self, pass: &mut wgpu::RenderPass<'_>, instances: Range<u32>) {
+        self.vertices.set_on(pass, 0, ..);
+        self.indices.set_on(pass, ..);
+        pass.draw_indexed(0..self.index_count(), 0, instances);
+    }
+
+    /// Draws one copy of the mesh per instance in `instances`, which is bound at slot 1.
+    pub fn draw_instances<T: VertexLayout>(
+        &self,
+        pass: &mut wgpu::RenderPass<'_>,
+        instances: &InstanceBuffer<T>,
+    ) {
+        if instances.count() == 0 {
+            return;
+        }
+        instances.set_on(pass, 1);
+        self.draw_instanced(pass, instances.instances());
+    }
+}
@@ -2,6 +2,7 @@ pub mod callback;
 pub mod camera;
 pub mod image;
 pub mod lowlevel;
+pub mod mesh;
 pub mod pipeline;
 pub mod textures;
 
@@ -47,6 +48,48 @@ impl CardinalDirection {
         }
     }
 
+    /// Snaps an arbitrary vector to the nearest cardinal direction.
+    ///
+    /// The axis with the largest-magnitude component wins, and its sign picks the direction.
+    /// Ties are resolved in X, Y, Z order, so a perfectly diagonal vector snaps to the X axis.
+    /// A zero component counts as positive.
+    pub fn from_normal(v: glam::Vec3) -> CardinalDirection {
+        let abs = v.abs();
+        if abs.x >= abs.y && abs.x >= abs.z {
+            if v.x >= 0.0 {
+                CardinalDirection::East
+            } else {
+                CardinalDirection::West
+            }
+        } else if abs.y >= abs.z {
+            if v.y >= 0.0 {
+                CardinalDirection::Up
+            } else {
+                CardinalDirection::Down
+            }
+        } else if v.z >= 0.0 {
+            CardinalDirection::South
+        } else {
+            CardinalDirection::North
+        }
+    }
+
+    /// Returns the rotation that maps the canonical forward axis, `+Z` (`South`), onto this direction's normal.
+    ///
+    /// East, West and North are rotations about the Y axis, so `+Y` stays up. Up and Down pitch about the X axis
+    /// instead, which takes `+Y` to `North` for Up and to `South` for Down.
+    pub fn rotation(&self) -> glam::Quat {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        match self {
+            CardinalDirection::South => glam::Quat::IDENTITY,
+            CardinalDirection::North => glam::Quat::from_rotation_y(PI),
+            CardinalDirection::East => glam::Quat::from_rotation_y(FRAC_PI_2),
+            CardinalDirection::West => glam::Quat::from_rotation_y(-FRAC_PI_2),
+            CardinalDirection::Up => glam::Quat::from_rotation_x(-FRAC_PI_2),
+            CardinalDirection::Down => glam::Quat::from_rotation_x(FRAC_PI_2),
+        }
+    }
+
     pub fn iter() -> impl Iterator<Item = CardinalDirection> {
         [
             CardinalDirection::North,
@@ -85,3 +128,59 @@ impl CardinalDirection {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CardinalDirection;
+    use glam::Vec3;
+
+    #[test]
+    fn test_from_normal_round_trip() {
+        for dir in CardinalDirection::iter() {
+            assert_eq!(CardinalDirection::from_normal(dir.normal()), dir);
+            assert_eq!(CardinalDirection::from_normal(dir.normal() * 3.5), dir);
+        }
+    }
+
+    #[test]
+    fn test_from_normal_snaps() {
+        assert_eq!(
+            CardinalDirection::from_normal(Vec3::new(0.2, -0.9, 0.3)),
+            CardinalDirection::Down
+        );
+        assert_eq!(
+            CardinalDirection::from_normal(Vec3::new(-0.1, 0.4, -0.8)),
+            CardinalDirection::North
+        );
+        // Ties resolve in X, Y, Z order.
+        assert_eq!(
+            CardinalDirection::from_normal(Vec3::new(-1.0, 1.0, 1.0)),
+            CardinalDirection::West
+        );
+        assert_eq!(
+            CardinalDirection::from_normal(Vec3::new(0.0, -1.0, 1.0)),
+            CardinalDirection::Down
+        );
+    }
+
+    #[test]
+    fn test_rotation_maps_canonical_to_normal() {
+        for dir in CardinalDirection::iter() {
+            let rotated = dir.rotation() * Vec3::Z;
+            assert!(
+                rotated.abs_diff_eq(dir.normal(), 1e-6),
+                "{dir:?}: {rotated} != {}",
+                dir.normal()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rotation_roll_convention() {
+        let up = |dir: CardinalDirection| dir.rotation() * Vec3::Y;
+        assert!(up(CardinalDirection::East).abs_diff_eq(Vec3::Y, 1e-6));
+        assert!(up(CardinalDirection::North).abs_diff_eq(Vec3::Y, 1e-6));
+        assert!(up(CardinalDirection::Up).abs_diff_eq(CardinalDirection::North.normal(), 1e-6));
+        assert!(up(CardinalDirection::Down).abs_diff_eq(CardinalDirection::South.normal(), 1e-6));
+    }
+}
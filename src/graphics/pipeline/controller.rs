@@ -1,6 +1,11 @@
-use std::{any::Any, fmt::Debug};
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+};
 
 use anyhow::Context;
+use log::debug;
 use wgpu::TextureView;
 
 use crate::{
@@ -8,8 +13,9 @@ use crate::{
     graphics::{
         lowlevel::WgpuRenderer,
         pipeline::{
-            DeltaTime, FrameCount, RenderPipeline, UpdateRequest, downcast_pipeline_mut,
-            downcast_pipeline_ref,
+            ClearColor, DeltaTime, DependencyCycle, ElapsedTime, FrameCount, RawDeltaTime,
+            RenderPipeline, UpdateRequest, downcast_pipeline_mut, downcast_pipeline_ref,
+            timing::GpuTimer,
         },
     },
 };
@@ -25,37 +31,119 @@ pub trait PipelineKey:
 ///
 /// Pretty much, you insert pipelines based off of the key type K, and then you can retrieve them later.
 ///
-/// You HAVE to set a render order, or nothing will be rendered. (via set_render_order)
+/// You HAVE to set a render order, or nothing will be rendered. (via set_render_order, or compute_render_order to
+/// derive it from each pipeline's dependencies)
+///
+/// Each frame is bracketed by `begin_frame` and `end_frame`, with `update` and `render_pipelines` in between.
 ///
 /// You can stash frame-specific data that can be accessed by pipelines during rendering. This data is cleared at the start of each frame before updating pipelines.
-/// This data by default contains a DeltaTime (time since last frame) and FrameCount (number of frames rendered so far).
+/// This data by default contains a DeltaTime (time since last frame, clamped to `max_delta`), a RawDeltaTime (the
+/// unclamped time since last frame), an ElapsedTime (the sum of every clamped DeltaTime since `reset_clock`) and
+/// FrameCount (number of frames rendered so far).
+///
+/// Long-lived render state can instead be stashed with `stash_persistent`, which is never cleared.
 ///
 pub struct RenderController<K: PipelineKey> {
     pipelines: std::collections::HashMap<K, Box<dyn RenderPipeline<K> + 'static>>,
+    // keys of `pipelines` in the order they were added, so `compute_render_order` is deterministic
+    registered: Vec<K>,
     render_list: Vec<K>,
-    render_suface: Option<(K, wgpu::TextureView)>,
+    disabled: HashSet<K>,
+    // pipelines that requested `SkipRender` this frame
+    skipped: HashSet<K>,
+    render_surface: Option<(K, wgpu::TextureView)>,
     frame_data: Stash,
+    persistent: Stash,
     frame_count: u64,
+    max_delta: f32,
+    // accumulated in f64, as f32 loses most of a frame's delta after an hour or so
+    elapsed: f64,
+    timer: Option<GpuTimer<K>>,
+    timings: HashMap<K, f32>,
     /// The WGPU renderer. Convenience access for pipelines.
     pub wgpu: ComponentHandle<WgpuRenderer>,
 }
 
 impl<K: PipelineKey> RenderController<K> {
+    /// The default upper bound of the stashed `DeltaTime`, in seconds.
+    pub const DEFAULT_MAX_DELTA: f32 = 0.1;
+
     /// Creates a new RenderController.
+    ///
+    /// If the store has already been finalized, this confirms its map is resolved (see `ComponentStore::resolve`),
+    /// so component lookups made while rendering don't block.
     pub fn new(state: &ComponentStore) -> Self {
+        if state.resolve().is_err() {
+            debug!("RenderController created before the component store was finalized");
+        }
         Self {
             pipelines: std::collections::HashMap::new(),
+            registered: Vec::new(),
             render_list: Vec::new(),
-            render_suface: None,
+            disabled: HashSet::new(),
+            skipped: HashSet::new(),
+            render_surface: None,
             wgpu: state.handle_for::<WgpuRenderer>(),
             frame_data: Stash::new(),
+            persistent: Stash::new(),
             frame_count: 0,
+            max_delta: Self::DEFAULT_MAX_DELTA,
+            elapsed: 0.0,
+            timer: None,
+            timings: HashMap::new(),
         }
     }
 
     /// Adds a render pipeline to the controller.
     pub fn add_pipeline<P: RenderPipeline<K> + 'static>(&mut self, key: K, pipeline: P) {
-        self.pipelines.insert(key, Box::new(pipeline));
+        if self
+            .pipelines
+            .insert(key.clone(), Box::new(pipeline))
+            .is_none()
+        {
+            self.registered.push(key);
+        }
+    }
+
+    /// Removes a render pipeline from the controller, returning it if it existed.
+    ///
+    /// The pipeline is also removed from the render order.
+    pub fn remove_pipeline(&mut self, key: &K) -> Option<Box<dyn RenderPipeline<K>>> {
+        let pipeline = self.pipelines.remove(key)?;
+        self.registered.retain(|k| k != key);
+        self.render_list.retain(|k| k != key);
+        self.disabled.remove(key);
+        self.skipped.remove(key);
+        if matches!(self.render_surface, Some((ref k, _)) if k == key) {
+            self.render_surface = None;
+        }
+        Some(pipeline)
+    }
+
+    /// Enables or disables a pipeline.
+    ///
+    /// Disabled pipelines stay registered and can still be retrieved, but are skipped when updating and rendering.
+    pub fn set_enabled(&mut self, key: &K, enabled: bool) {
+        if enabled {
+            self.disabled.remove(key);
+        } else {
+            self.disabled.insert(key.clone());
+        }
+    }
+
+    /// Returns true if the pipeline is enabled. Pipelines are enabled by default.
+    pub fn is_enabled(&self, key: &K) -> bool {
+        !self.disabled.contains(key)
+    }
+
+    /// Returns true if the pipeline requested `UpdateRequest::SkipRender` during this frame's update.
+    pub fn is_skipped(&self, key: &K) -> bool {
+        self.skipped.contains(key)
+    }
+
+    /// Returns true if the pipeline should be rendered this frame.
+    fn should_render(&self, key: &K) -> bool {
+        self.is_enabled(key) && !self.is_skipped(key)
     }
 
     /// Retrieves a mutable reference to a render pipeline by its key.
@@ -73,57 +161,225 @@ impl<K: PipelineKey> RenderController<K> {
         self.pipelines.get(key).map(|p| p.as_ref())
     }
 
+    /// Returns an iterator over the keys of every registered pipeline, in no particular order.
+    pub fn pipeline_keys(&self) -> impl Iterator<Item = &K> {
+        self.pipelines.keys()
+    }
+
+    /// Returns true if the key is part of the render order.
+    ///
+    /// A registered pipeline that is not in the render order is never updated or rendered.
+    pub fn is_in_render_order(&self, key: &K) -> bool {
+        self.render_list.contains(key)
+    }
+
+    /// Returns the label of a pipeline by its key.
+    /// Returns None if the pipeline does not exist or has no label.
+    pub fn label_of(&self, key: &K) -> Option<&str> {
+        self.pipelines.get(key)?.label()
+    }
+
+    /// Returns the key of the pipeline that set the render target for this frame, if any.
+    ///
+    /// While a target is set, that pipeline renders to the swap chain and every other pipeline renders to the
+    /// target instead. See `UpdateRequest::SetRenderTarget`.
+    pub fn current_render_target(&self) -> Option<&K> {
+        self.render_surface.as_ref().map(|(key, _)| key)
+    }
+
+    /// Clears the render target set by `UpdateRequest::SetRenderTarget`, so every pipeline renders to the swap chain
+    /// again.
+    ///
+    /// Targets are already cleared by `begin_frame` and `end_frame`; this is for dropping one mid-frame.
+    pub fn clear_render_target(&mut self) {
+        self.render_surface = None;
+    }
+
     /// Sets the render order of the pipelines. This must be set, or no pipelines will be rendered.
-    pub fn set_render_order(&mut self, order: Vec<K>) {
+    ///
+    /// If any key in the order has no registered pipeline, the render order is left unchanged and those keys are
+    /// returned. Use `set_render_order_unchecked` to register the pipelines after setting the order.
+    pub fn set_render_order(&mut self, order: Vec<K>) -> Result<(), Vec<K>> {
+        let missing = order
+            .iter()
+            .filter(|k| !self.pipelines.contains_key(k))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(missing);
+        }
         self.render_list = order;
+        Ok(())
     }
 
-    fn handle_update_request(&mut self, source: &K, request: UpdateRequest) {
+    /// Sets the render order of the pipelines without checking that every key has a registered pipeline.
+    ///
+    /// Rendering fails if a key is still unregistered by the time `render_pipelines` is called.
+    pub fn set_render_order_unchecked(&mut self, order: Vec<K>) {
+        self.render_list = order;
+    }
+
+    /// Sets the render order by topologically sorting the registered pipelines by their `dependencies`.
+    ///
+    /// Pipelines that don't depend on each other keep the order they were added in. Dependencies on keys that are not
+    /// registered are ignored. Calling `set_render_order` afterwards overrides the computed order.
+    ///
+    /// On a cycle, the render order is left unchanged and the keys forming the cycle are returned.
+    pub fn compute_render_order(&mut self) -> Result<(), DependencyCycle<K>> {
+        let dependencies = self
+            .pipelines
+            .iter()
+            .map(|(k, p)| (k.clone(), p.dependencies()))
+            .collect::<HashMap<_, _>>();
+
+        let mut order = Vec::with_capacity(dependencies.len());
+        let mut done = HashSet::new();
+        let mut path = Vec::new();
+        for key in &self.registered {
+            visit_pipeline(key, &dependencies, &mut done, &mut path, &mut order)?;
+        }
+
+        self.render_list = order;
+        Ok(())
+    }
+
+    fn handle_update_request(&mut self, source: &K, request: UpdateRequest, stash: &mut Stash) {
         match request {
             UpdateRequest::SetRenderTarget(view) => {
-                self.render_suface = Some((source.clone(), view));
+                self.render_surface = Some((source.clone(), view));
+            }
+            UpdateRequest::SetClearColor(color) => {
+                stash.stash(ClearColor(color));
+            }
+            UpdateRequest::RequestResize(width, height) => {
+                self.wgpu
+                    .get()
+                    .resize(winit::dpi::PhysicalSize::new(width, height));
+            }
+            UpdateRequest::SkipRender => {
+                self.skipped.insert(source.clone());
             }
         }
     }
 
-    /// Updates all pipelines managed by the controller.
-    pub fn update_pipelines(&mut self, delta_time: f32) {
-        let mut stash = Stash::new();
-        stash.stash(DeltaTime(delta_time));
+    /// Sets the upper bound of the stashed `DeltaTime`, in seconds. `RawDeltaTime` is never clamped.
+    pub fn set_max_delta(&mut self, max_delta: f32) {
+        self.max_delta = max_delta;
+    }
+
+    /// Returns the upper bound of the stashed `DeltaTime`, in seconds. Defaults to `DEFAULT_MAX_DELTA`.
+    pub fn max_delta(&self) -> f32 {
+        self.max_delta
+    }
+
+    /// Resets the stashed `ElapsedTime` to zero, e.g. on a scene transition.
+    ///
+    /// The frame data of the current frame is left as is; the next `begin_frame` counts from zero.
+    pub fn reset_clock(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    /// Begins a new frame.
+    ///
+    /// This rebuilds the frame data with the default `DeltaTime`, `RawDeltaTime`, `ElapsedTime` and `FrameCount`,
+    /// increments the frame count, and discards the render target of the previous frame. Data stashed after this call
+    /// is visible to pipelines during `update` and rendering.
+    ///
+    /// A frame is `begin_frame`, `update`, `render_pipelines`, then `end_frame`.
+    pub fn begin_frame(&mut self, delta_time: f32) {
+        if let Some(timer) = &self.timer
+            && let Some(timings) = timer.collect(&self.wgpu.read())
+        {
+            self.timings = timings;
+        }
+
+        // a target or skip only ever applies to the frame it was requested in
+        self.render_surface = None;
+        self.skipped.clear();
+
         self.frame_count += 1;
+        let mut stash = Stash::new();
+        let clamped = delta_time.min(self.max_delta);
+        self.elapsed += clamped as f64;
+        stash.stash(DeltaTime(clamped));
+        stash.stash(RawDeltaTime(delta_time));
+        stash.stash(ElapsedTime(self.elapsed as f32));
         stash.stash(FrameCount(self.frame_count));
+        self.frame_data = stash;
+    }
+
+    /// Updates all enabled pipelines in render order, using the frame data of the current frame.
+    pub fn update(&mut self) {
+        let mut stash = std::mem::take(&mut self.frame_data);
         for i in 0..self.render_list.len() {
             let pipeline_key = &self.render_list[i].clone();
+            if !self.is_enabled(pipeline_key) {
+                continue;
+            }
             let pipeline = self.get_pipeline_mut(pipeline_key).unwrap();
             if let Some(request) = pipeline.update(&mut stash) {
-                self.handle_update_request(pipeline_key, request);
+                self.handle_update_request(pipeline_key, request, &mut stash);
             }
         }
         self.frame_data = stash;
     }
 
+    /// Ends the current frame, resetting any per-frame render target.
+    pub fn end_frame(&mut self) {
+        self.render_surface = None;
+    }
+
+    /// Begins a new frame and updates all pipelines managed by the controller.
+    ///
+    /// Equivalent to `begin_frame` followed by `update`.
+    pub fn update_pipelines(&mut self, delta_time: f32) {
+        self.begin_frame(delta_time);
+        self.update();
+    }
+
     /// Renders all pipelines in the order specified by `set_render_order`.
+    ///
+    /// If multisampling is enabled with `WgpuRenderer::set_msaa`, pipelines are given the multisampled target in
+    /// place of the swap chain, and it is resolved into the swap chain after the last pipeline. Multisampling can't
+    /// be combined with `UpdateRequest::SetRenderTarget`; rendering with both returns an error without recording
+    /// anything.
+    ///
+    /// If the swap chain texture can't be acquired, the returned error is the `SurfaceError` itself, so it can be
+    /// recovered with `err.downcast_ref::<SurfaceError>()`.
     pub fn render_pipelines(
         &self,
         encoder: &mut wgpu::CommandEncoder,
     ) -> anyhow::Result<(wgpu::SurfaceTexture, TextureView)> {
         let wgpu = self.wgpu.get();
-        let (surf, swapchain_texture) = wgpu
-            .current_view()
-            .with_context(|| "Failed to get swapchain texture")?;
-
-        if let Some((ref key, ref target)) = self.render_suface {
-            self.render_with_target(encoder, &swapchain_texture, key, target)?;
-            return Ok((surf, swapchain_texture));
+        let msaa = wgpu.msaa_target();
+        if let (Some(msaa), Some((key, _))) = (&msaa, &self.render_surface) {
+            anyhow::bail!(
+                "pipeline {:?} set a render target, which can't be used with {}x MSAA",
+                key,
+                msaa.sample_count()
+            );
         }
+        let (surf, swapchain_texture) = wgpu.current_view()?;
+        let output = msaa.as_ref().map_or(&swapchain_texture, |msaa| &msaa.view);
 
-        for pipeline_key in &self.render_list {
-            let pipeline = self
-                .get_pipeline(pipeline_key)
-                .with_context(|| format!("Pipeline {:?} not found in controller", pipeline_key))?;
-            pipeline.render(self, encoder, &swapchain_texture);
+        if let Some((ref key, ref target)) = self.render_surface {
+            self.render_with_target(encoder, output, key, target)?;
+        } else {
+            let timer = self.timer.as_ref().filter(|t| t.begin());
+            for pipeline_key in &self.render_list {
+                if !self.should_render(pipeline_key) {
+                    continue;
+                }
+                self.render_one(timer, encoder, pipeline_key, output)?;
+            }
+            if let Some(timer) = timer {
+                timer.finish(encoder);
+            }
         }
 
+        if let Some(msaa) = &msaa {
+            msaa.resolve(encoder, &swapchain_texture);
+        }
         Ok((surf, swapchain_texture))
     }
 
@@ -134,19 +390,61 @@ impl<K: PipelineKey> RenderController<K> {
         key: &K,
         target: &wgpu::TextureView,
     ) -> anyhow::Result<()> {
+        let timer = self.timer.as_ref().filter(|t| t.begin());
         for pipeline_key in &self.render_list {
-            let pipeline = self
-                .get_pipeline(pipeline_key)
-                .with_context(|| format!("Pipeline {:?} not found in controller", pipeline_key))?;
+            if !self.should_render(pipeline_key) {
+                continue;
+            }
             if pipeline_key == key {
-                pipeline.render(self, encoder, output);
+                self.render_one(timer, encoder, pipeline_key, output)?;
                 continue;
             }
-            pipeline.render(self, encoder, target);
+            self.render_one(timer, encoder, pipeline_key, target)?;
+        }
+        if let Some(timer) = timer {
+            timer.finish(encoder);
+        }
+        Ok(())
+    }
+
+    /// Renders a single pipeline, recording timestamps around it if timing is active this frame.
+    fn render_one(
+        &self,
+        timer: Option<&GpuTimer<K>>,
+        encoder: &mut wgpu::CommandEncoder,
+        key: &K,
+        target: &wgpu::TextureView,
+    ) -> anyhow::Result<()> {
+        let pipeline = self
+            .get_pipeline(key)
+            .with_context(|| format!("Pipeline {:?} not found in controller", key))?;
+
+        let slot = timer.and_then(|t| t.start(encoder, key));
+        pipeline.render(self, encoder, target);
+        if let (Some(timer), Some(slot)) = (timer, slot) {
+            timer.end(encoder, slot);
         }
         Ok(())
     }
 
+    /// Enables GPU timing of each pipeline's `render` call.
+    ///
+    /// Returns false (and does nothing) if the device lacks `TIMESTAMP_QUERY` and `TIMESTAMP_QUERY_INSIDE_ENCODERS`.
+    pub fn enable_gpu_timing(&mut self) -> bool {
+        if self.timer.is_none() {
+            self.timer = GpuTimer::new(&self.wgpu.read());
+        }
+        self.timer.is_some()
+    }
+
+    /// Returns the GPU time spent in each pipeline, in milliseconds.
+    ///
+    /// Timings are read back without blocking, so they lag a few frames behind. This is empty unless
+    /// `enable_gpu_timing` succeeded.
+    pub fn last_frame_timings(&self) -> &HashMap<K, f32> {
+        &self.timings
+    }
+
     /// Retrieves a reference to a pipeline of the specified type.
     pub fn pipeline<P: RenderPipeline<K> + 'static>(&self, key: &K) -> anyhow::Result<&P> {
         downcast_pipeline_ref::<K, P>(self, key)?
@@ -180,6 +478,86 @@ impl<K: PipelineKey> RenderController<K> {
         self.retrieve_checked::<T>()
             .expect("Requested frame data not found")
     }
+
+    /// Retrieves a mutable reference to stashed frame-specific data of the specified type.
+    /// Returns None if no such data exists.
+    pub fn retrieve_mut_checked<T: 'static + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.frame_data.retrieve_mut_checked::<T>()
+    }
+
+    /// Retrieves a mutable reference to stashed frame-specific data of the specified type.
+    /// Panics if no such data exists. Use `retrieve_mut_checked` if you want to handle the absence of data more gracefully.
+    pub fn retrieve_mut<T: 'static + Send + Sync>(&mut self) -> &mut T {
+        self.retrieve_mut_checked::<T>()
+            .expect("Requested frame data not found")
+    }
+
+    /// Removes stashed frame-specific data of the specified type and returns it, handing ownership to the caller.
+    /// Returns None if no such data exists.
+    pub fn take_frame_data<T: 'static + Send + Sync>(&mut self) -> Option<T> {
+        self.frame_data.take::<T>()
+    }
+
+    /// Stashes data that persists across frames, unlike the frame-specific data from `stash`.
+    pub fn stash_persistent<T: 'static + Send + Sync>(&mut self, data: T) {
+        self.persistent.stash(data);
+    }
+
+    /// Retrieves a reference to persistent data of the specified type.
+    /// Returns None if no such data exists.
+    pub fn retrieve_persistent_checked<T: 'static + Send + Sync>(&self) -> Option<&T> {
+        self.persistent.retrieve_checked::<T>()
+    }
+
+    /// Retrieves a reference to persistent data of the specified type.
+    /// Panics if no such data exists. Use `retrieve_persistent_checked` to handle the absence of data more gracefully.
+    pub fn retrieve_persistent<T: 'static + Send + Sync>(&self) -> &T {
+        self.retrieve_persistent_checked::<T>()
+            .expect("Requested persistent data not found")
+    }
+
+    /// Retrieves a mutable reference to persistent data of the specified type, so it can be updated in place.
+    /// Returns None if no such data exists.
+    pub fn retrieve_persistent_mut_checked<T: 'static + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.persistent.retrieve_mut_checked::<T>()
+    }
+
+    /// Retrieves a mutable reference to persistent data of the specified type, so it can be updated in place.
+    /// Panics if no such data exists. Use `retrieve_persistent_mut_checked` to handle the absence of data more
+    /// gracefully.
+    pub fn retrieve_persistent_mut<T: 'static + Send + Sync>(&mut self) -> &mut T {
+        self.retrieve_persistent_mut_checked::<T>()
+            .expect("Requested persistent data not found")
+    }
+}
+
+/// Depth-first visit for `compute_render_order`. `path` holds the keys currently being visited, so reaching one of
+/// them again means there is a cycle.
+fn visit_pipeline<K: PipelineKey>(
+    key: &K,
+    dependencies: &HashMap<K, Vec<K>>,
+    done: &mut HashSet<K>,
+    path: &mut Vec<K>,
+    order: &mut Vec<K>,
+) -> Result<(), DependencyCycle<K>> {
+    if done.contains(key) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|k| k == key) {
+        return Err(DependencyCycle(path[start..].to_vec()));
+    }
+
+    path.push(key.clone());
+    for dependency in &dependencies[key] {
+        if dependencies.contains_key(dependency) {
+            visit_pipeline(dependency, dependencies, done, path, order)?;
+        }
+    }
+    path.pop();
+
+    done.insert(key.clone());
+    order.push(key.clone());
+    Ok(())
 }
 
 impl<K: PipelineKey> Debug for RenderController<K> {
@@ -227,8 +605,316 @@ impl Stash {
             .expect("Requested stashed data not found")
     }
 
+    /// Retrieves a mutable reference to stashed data of the specified type.
+    pub fn retrieve_mut_checked<T: 'static + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.inner.get_mut::<T>()
+    }
+
+    /// Retrieves a mutable reference to stashed data of the specified type.
+    pub fn retrieve_mut<T: 'static + Send + Sync>(&mut self) -> &mut T {
+        self.retrieve_mut_checked::<T>()
+            .expect("Requested stashed data not found")
+    }
+
+    /// Retrieves a mutable reference to stashed data of the specified type, stashing `T::default()` if it is absent.
+    pub fn retrieve_or_default<T: 'static + Send + Sync + Default>(&mut self) -> &mut T {
+        self.inner.get_or_default::<T>()
+    }
+
+    /// Removes stashed data of the specified type and returns it, if it exists.
+    pub fn take<T: 'static + Send + Sync>(&mut self) -> Option<T> {
+        self.inner.remove::<T>()
+    }
+
+    /// Returns true if data of the specified type is stashed.
+    pub fn contains<T: 'static + Send + Sync>(&self) -> bool {
+        self.inner.contains::<T>()
+    }
+
+    /// Returns an iterator over the type names of the stashed data, e.g. for a debug overlay.
+    pub fn iter_type_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.inner.iter_type_names()
+    }
+
     /// Removes all stashed data.
     pub fn clear(&mut self) {
         self.inner.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        component::ComponentStore,
+        graphics::pipeline::{
+            ClearColor, DeltaTime, ElapsedTime, FrameCount, RawDeltaTime, RenderPipeline,
+            UpdateRequest,
+            controller::{PipelineKey, RenderController, Stash},
+        },
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Key {
+        Clear,
+        Geometry,
+        Post,
+        Missing,
+    }
+
+    impl PipelineKey for Key {}
+
+    /// Requests a clear color on even frames and records the frames it saw.
+    #[derive(Default)]
+    struct Recorder(Vec<u64>);
+
+    impl RenderPipeline<Key> for Recorder {
+        fn label(&self) -> Option<&str> {
+            Some("Recorder")
+        }
+
+        fn update(&mut self, stash: &mut Stash) -> Option<UpdateRequest> {
+            let frame = stash.retrieve::<FrameCount>().0;
+            self.0.push(frame);
+            (frame % 2 == 0).then_some(UpdateRequest::SetClearColor(wgpu::Color::RED))
+        }
+
+        fn render(
+            &self,
+            _controller: &RenderController<Key>,
+            _encoder: &mut wgpu::CommandEncoder,
+            _target: &wgpu::TextureView,
+        ) {
+        }
+    }
+
+    /// Does nothing, but depends on the given pipelines.
+    struct Pass(Vec<Key>);
+
+    impl RenderPipeline<Key> for Pass {
+        fn label(&self) -> Option<&str> {
+            Some("Pass")
+        }
+
+        fn update(&mut self, _stash: &mut Stash) -> Option<UpdateRequest> {
+            None
+        }
+
+        fn dependencies(&self) -> Vec<Key> {
+            self.0.clone()
+        }
+
+        fn render(
+            &self,
+            _controller: &RenderController<Key>,
+            _encoder: &mut wgpu::CommandEncoder,
+            _target: &wgpu::TextureView,
+        ) {
+        }
+    }
+
+    #[test]
+    fn test_compute_render_order() {
+        let store = ComponentStore::new();
+        let mut controller = RenderController::<Key>::new(&store);
+        controller.add_pipeline(Key::Post, Pass(vec![Key::Geometry, Key::Clear]));
+        controller.add_pipeline(Key::Geometry, Pass(vec![Key::Clear]));
+        controller.add_pipeline(Key::Clear, Pass(vec![Key::Missing]));
+
+        controller.compute_render_order().unwrap();
+        assert_eq!(
+            controller.render_list,
+            vec![Key::Clear, Key::Geometry, Key::Post]
+        );
+    }
+
+    #[test]
+    fn test_pipeline_introspection() {
+        let store = ComponentStore::new();
+        let mut controller = RenderController::<Key>::new(&store);
+        controller.add_pipeline(Key::Clear, Pass(vec![]));
+        controller.add_pipeline(Key::Post, Recorder::default());
+        controller.set_render_order(vec![Key::Clear]).unwrap();
+
+        let mut keys = controller.pipeline_keys().copied().collect::<Vec<_>>();
+        keys.sort_by_key(|k| *k as u8);
+        assert_eq!(keys, vec![Key::Clear, Key::Post]);
+
+        assert!(controller.is_in_render_order(&Key::Clear));
+        assert!(!controller.is_in_render_order(&Key::Post));
+        assert_eq!(controller.label_of(&Key::Post), Some("Recorder"));
+        assert_eq!(controller.label_of(&Key::Missing), None);
+    }
+
+    #[test]
+    fn test_set_render_order_rejects_unregistered() {
+        let store = ComponentStore::new();
+        let mut controller = RenderController::<Key>::new(&store);
+        controller.add_pipeline(Key::Clear, Pass(vec![]));
+
+        let missing = controller
+            .set_render_order(vec![Key::Clear, Key::Missing, Key::Post])
+            .unwrap_err();
+        assert_eq!(missing, vec![Key::Missing, Key::Post]);
+        assert!(controller.render_list.is_empty());
+
+        controller.set_render_order_unchecked(vec![Key::Clear, Key::Post]);
+        assert_eq!(controller.render_list, vec![Key::Clear, Key::Post]);
+    }
+
+    #[test]
+    fn test_compute_render_order_keeps_registration_order() {
+        let store = ComponentStore::new();
+        let mut controller = RenderController::<Key>::new(&store);
+        controller.add_pipeline(Key::Post, Pass(vec![]));
+        controller.add_pipeline(Key::Missing, Pass(vec![]));
+        controller.add_pipeline(Key::Clear, Pass(vec![]));
+        controller.add_pipeline(Key::Geometry, Pass(vec![Key::Clear]));
+        // replacing a pipeline keeps its original position
+        controller.add_pipeline(Key::Post, Pass(vec![]));
+
+        controller.compute_render_order().unwrap();
+        assert_eq!(
+            controller.render_list,
+            vec![Key::Post, Key::Missing, Key::Clear, Key::Geometry]
+        );
+    }
+
+    #[test]
+    fn test_compute_render_order_cycle() {
+        let store = ComponentStore::new();
+        let mut controller = RenderController::<Key>::new(&store);
+        controller.add_pipeline(Key::Clear, Pass(vec![]));
+        controller.add_pipeline(Key::Geometry, Pass(vec![Key::Clear, Key::Post]));
+        controller.add_pipeline(Key::Post, Pass(vec![Key::Geometry]));
+        controller.set_render_order(vec![Key::Clear]).unwrap();
+
+        let mut cycle = controller.compute_render_order().unwrap_err().0;
+        cycle.sort_by_key(|k| *k as u8);
+        assert_eq!(cycle, vec![Key::Geometry, Key::Post]);
+        assert_eq!(controller.render_list, vec![Key::Clear]);
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Exposure(f32);
+
+    #[test]
+    fn test_stash_retrieve_or_default() {
+        let mut stash = Stash::new();
+        assert_eq!(*stash.retrieve_or_default::<Exposure>(), Exposure(0.0));
+
+        stash.retrieve_or_default::<Exposure>().0 = 2.0;
+        assert_eq!(stash.retrieve::<Exposure>(), &Exposure(2.0));
+
+        stash.retrieve_mut::<Exposure>().0 += 1.0;
+        assert_eq!(stash.retrieve::<Exposure>(), &Exposure(3.0));
+        assert!(stash.retrieve_mut_checked::<u32>().is_none());
+    }
+
+    #[test]
+    fn test_stash_take() {
+        let mut stash = Stash::new();
+        stash.stash(vec![1u32, 2, 3]);
+
+        assert_eq!(stash.take::<Vec<u32>>(), Some(vec![1, 2, 3]));
+        assert!(!stash.contains::<Vec<u32>>());
+        assert_eq!(stash.take::<Vec<u32>>(), None);
+    }
+
+    #[test]
+    fn test_frame_lifecycle() {
+        let store = ComponentStore::new();
+        let mut controller = RenderController::<Key>::new(&store);
+        controller.add_pipeline(Key::Clear, Recorder::default());
+        controller.set_render_order(vec![Key::Clear]).unwrap();
+
+        controller.begin_frame(0.5);
+        controller.stash(7u32);
+        controller.update();
+        assert_eq!(controller.retrieve::<DeltaTime>().0, 0.1);
+        assert_eq!(controller.retrieve::<RawDeltaTime>().0, 0.5);
+        assert_eq!(controller.retrieve::<FrameCount>().0, 1);
+        assert_eq!(*controller.retrieve::<u32>(), 7);
+        assert_eq!(controller.take_frame_data::<u32>(), Some(7));
+        assert!(controller.retrieve_checked::<u32>().is_none());
+        assert!(controller.retrieve_checked::<ClearColor>().is_none());
+        controller.end_frame();
+
+        controller.set_max_delta(1.0);
+        controller.begin_frame(0.25);
+        assert!(controller.retrieve_checked::<u32>().is_none());
+        controller.update();
+        assert_eq!(controller.retrieve::<DeltaTime>().0, 0.25);
+        assert_eq!(controller.retrieve::<RawDeltaTime>().0, 0.25);
+        assert_eq!(controller.retrieve::<ElapsedTime>().0, 0.35);
+        assert_eq!(controller.retrieve::<FrameCount>().0, 2);
+        assert_eq!(controller.retrieve::<ClearColor>().0, wgpu::Color::RED);
+        controller.end_frame();
+
+        controller.reset_clock();
+        controller.stash_persistent(3u64);
+        controller.begin_frame(0.1);
+        assert_eq!(controller.retrieve::<ElapsedTime>().0, 0.1);
+        assert_eq!(*controller.retrieve_persistent::<u64>(), 3);
+        assert!(controller.retrieve_persistent_checked::<u32>().is_none());
+        *controller.retrieve_persistent_mut::<u64>() += 1;
+        controller.end_frame();
+
+        // persistent data is updated in place across frames
+        controller.begin_frame(0.1);
+        assert_eq!(*controller.retrieve_persistent::<u64>(), 4);
+        *controller.retrieve_persistent_mut::<u64>() += 1;
+        assert!(
+            controller
+                .retrieve_persistent_mut_checked::<u32>()
+                .is_none()
+        );
+        controller.end_frame();
+        assert_eq!(*controller.retrieve_persistent::<u64>(), 5);
+
+        let recorder = controller.pipeline::<Recorder>(&Key::Clear).unwrap();
+        assert_eq!(recorder.0, vec![1, 2]);
+    }
+
+    /// Skips rendering on odd frames.
+    struct Skipper;
+
+    impl RenderPipeline<Key> for Skipper {
+        fn label(&self) -> Option<&str> {
+            Some("Skipper")
+        }
+
+        fn update(&mut self, stash: &mut Stash) -> Option<UpdateRequest> {
+            (stash.retrieve::<FrameCount>().0 % 2 == 1).then_some(UpdateRequest::SkipRender)
+        }
+
+        fn render(
+            &self,
+            _controller: &RenderController<Key>,
+            _encoder: &mut wgpu::CommandEncoder,
+            _target: &wgpu::TextureView,
+        ) {
+        }
+    }
+
+    #[test]
+    fn test_skip_render_lasts_one_frame() {
+        let store = ComponentStore::new();
+        let mut controller = RenderController::<Key>::new(&store);
+        controller.add_pipeline(Key::Post, Skipper);
+        controller.add_pipeline(Key::Clear, Pass(vec![]));
+        controller
+            .set_render_order(vec![Key::Clear, Key::Post])
+            .unwrap();
+
+        controller.update_pipelines(0.1);
+        assert!(controller.is_skipped(&Key::Post));
+        assert!(!controller.should_render(&Key::Post));
+        assert!(controller.should_render(&Key::Clear));
+        controller.end_frame();
+
+        controller.begin_frame(0.1);
+        assert!(!controller.is_skipped(&Key::Post));
+        controller.update();
+        assert!(controller.should_render(&Key::Post));
+    }
+}
@@ -5,6 +5,7 @@ use std::any::Any;
 
 pub mod controller;
 pub mod pipelines;
+mod timing;
 
 /// A trait representing a render pipeline.
 pub trait RenderPipeline<K: PipelineKey>: Send + Sync + 'static + Any {
@@ -14,11 +15,19 @@ pub trait RenderPipeline<K: PipelineKey>: Send + Sync + 'static + Any {
     ///
     /// Gives the pipeline access to the frame-specific stash data.
     ///
-    /// This frame-specific data can be added to, and by default includes `DeltaTime` and `FrameCount`.
+    /// This frame-specific data can be added to, and by default includes `DeltaTime`, `RawDeltaTime`, `ElapsedTime` and
+    /// `FrameCount`.
     ///
     /// Returns an optional UpdateRequest to modify the rendering process.
     fn update(&mut self, stash: &mut Stash) -> Option<UpdateRequest>;
 
+    /// Returns the keys of the pipelines that must render before this one.
+    ///
+    /// Used by `RenderController::compute_render_order`. Defaults to no dependencies.
+    fn dependencies(&self) -> Vec<K> {
+        Vec::new()
+    }
+
     /// Renders using the pipeline.
     ///
     /// Gives the pipeline access to the controller, command encoder, and target texture view.
@@ -35,7 +44,17 @@ pub trait RenderPipeline<K: PipelineKey>: Send + Sync + 'static + Any {
 pub enum UpdateRequest {
     /// Sets the render target that the pipeline should render to.
     /// The pipeline that provides this request will be given the swap chain's current texture as the target.
+    ///
+    /// This can't be combined with multisampling (`WgpuRenderer::set_msaa`), as the other pipelines are built for
+    /// the multisampled swap chain and can't render to a single sampled target. `render_pipelines` returns an error
+    /// while both are in use.
     SetRenderTarget(wgpu::TextureView),
+    /// Sets the clear color for this frame by stashing a `ClearColor`, which `ClearPipeline` uses over its own color.
+    SetClearColor(wgpu::Color),
+    /// Resizes the surface to the given width and height.
+    RequestResize(u32, u32),
+    /// Skips rendering the requesting pipeline for this frame only. It is updated again next frame as usual.
+    SkipRender,
 }
 
 /// Attempts to downcast a pipeline to a specific type.
@@ -75,10 +94,28 @@ pub fn downcast_pipeline_mut<'a, K: PipelineKey, P: RenderPipeline<K> + Sized +
 #[error("Pipeline is not of the expected type")]
 pub struct IncorrectPipelineType;
 
-/// Frame time delta in seconds. This is included in the frame data by default.
+/// Returned by `RenderController::compute_render_order` when pipelines depend on each other in a cycle.
+#[derive(thiserror::Error, Debug)]
+#[error("Pipeline dependency cycle: {0:?}")]
+pub struct DependencyCycle<K: PipelineKey>(pub Vec<K>);
+
+/// Frame time delta in seconds, clamped to `RenderController::max_delta`. This is included in the frame data by
+/// default.
+///
+/// The clamp keeps a hitch, such as a window drag, from making anything driven by the delta jump.
 #[repr(transparent)]
 pub struct DeltaTime(pub f32);
 
+/// Frame time delta in seconds, exactly as passed to `RenderController::begin_frame`. This is included in the frame
+/// data by default.
+#[repr(transparent)]
+pub struct RawDeltaTime(pub f32);
+
+/// Time in seconds since the controller was created or `RenderController::reset_clock` was last called, summed from
+/// the clamped `DeltaTime`. This is included in the frame data by default.
+#[repr(transparent)]
+pub struct ElapsedTime(pub f32);
+
 /// Frame count that counts the number of frames rendered since a undefined starting point. This is included in the frame data by default.
 #[repr(transparent)]
 pub struct FrameCount(pub u64);
@@ -87,4 +124,8 @@ pub struct FrameCount(pub u64);
 #[repr(transparent)]
 pub struct ClearColor(pub wgpu::Color);
 
+/// Exposure multiplier for tonemapping. This is not included by default; stash `Camera::exposure` to provide it.
+#[repr(transparent)]
+pub struct Exposure(pub f32);
+
 // TODO: Add more built-in frame data types as needed.
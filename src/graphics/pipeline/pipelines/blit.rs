@@ -0,0 +1,247 @@
+use wgpu::{LoadOp, TextureFormat};
+
+use crate::{
+    component::{ComponentHandle, ComponentStore},
+    graphics::{
+        lowlevel::{
+            WgpuRenderer, fullscreen,
+            pipeline::{PipelineBuilder, WgpuPipeline},
+        },
+        pipeline::{
+            RenderPipeline, UpdateRequest,
+            controller::{PipelineKey, RenderController, Stash},
+        },
+    },
+};
+
+/// The bindings available to a blit fragment shader. These are prepended to the shader source by `BlitPipeline`.
+pub const BLIT_BINDINGS: &str = r#"
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+"#;
+
+/// The entry point of [`COPY_SHADER`].
+pub const COPY_ENTRY: &str = "blit_copy";
+
+/// A fragment shader that copies the source texture unchanged.
+pub const COPY_SHADER: &str = r#"
+@fragment
+fn blit_copy(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(src, src_sampler, in.uv);
+}
+"#;
+
+/// Samples a source texture with a fragment shader and writes the result over the whole target.
+///
+/// This is the base for simple post-process passes such as copies, tonemapping or FXAA. The fragment shader is
+/// given [`BLIT_BINDINGS`] and the full-screen vertex output (see [`fullscreen`]), so it only needs to declare its
+/// entry point:
+///
+/// ```wgsl
+/// @fragment
+/// fn invert(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+///     let color = textureSample(src, src_sampler, in.uv);
+///     return vec4<f32>(1.0 - color.rgb, color.a);
+/// }
+/// ```
+///
+/// The pipeline renders with `WgpuRenderer::sample_count`, and is rebuilt in `update` when it changes.
+pub struct BlitPipeline {
+    label: String,
+    pipeline: WgpuPipeline,
+    source_code: String,
+    fragment_entry: String,
+    target_format: TextureFormat,
+    // the sample count `pipeline` was built with
+    sample_count: u32,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    group: wgpu::BindGroup,
+    wgpu_handle: ComponentHandle<WgpuRenderer>,
+}
+
+impl BlitPipeline {
+    /// Creates a blit pipeline running `fragment_entry` from `fragment_source` over `source`.
+    ///
+    /// `target_format` is the format of the texture the pipeline renders to. The source must be a filterable float
+    /// texture.
+    pub fn new(
+        state: &ComponentStore,
+        label: &str,
+        source: &wgpu::TextureView,
+        fragment_source: &str,
+        fragment_entry: &str,
+        target_format: TextureFormat,
+    ) -> Self {
+        let wgpu = state.get::<WgpuRenderer>();
+
+        let layout = wgpu.bind_group_layout(
+            Some(label),
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        );
+        let sampler = wgpu.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let source_code = fullscreen::with_fragment(&format!("{BLIT_BINDINGS}\n{fragment_source}"));
+        let sample_count = wgpu.sample_count();
+        let pipeline = create_pipeline(
+            &wgpu,
+            label,
+            &source_code,
+            fragment_entry,
+            &layout,
+            target_format,
+            sample_count,
+        );
+
+        let group = create_group(&wgpu, label, &layout, &sampler, source);
+
+        Self {
+            label: label.to_string(),
+            pipeline,
+            source_code,
+            fragment_entry: fragment_entry.to_string(),
+            target_format,
+            sample_count,
+            layout,
+            sampler,
+            group,
+            wgpu_handle: state.handle_for(),
+        }
+    }
+
+    /// Creates a blit pipeline that copies `source` to the target unchanged.
+    pub fn copy(
+        state: &ComponentStore,
+        source: &wgpu::TextureView,
+        target_format: TextureFormat,
+    ) -> Self {
+        Self::new(
+            state,
+            "Blit Copy Pipeline",
+            source,
+            COPY_SHADER,
+            COPY_ENTRY,
+            target_format,
+        )
+    }
+
+    /// Changes the texture the pipeline samples from, e.g. after the source was resized.
+    pub fn set_source(&mut self, source: &wgpu::TextureView) {
+        let wgpu = self.wgpu_handle.get();
+        self.group = create_group(&wgpu, &self.label, &self.layout, &self.sampler, source);
+    }
+}
+
+impl<K: PipelineKey> RenderPipeline<K> for BlitPipeline {
+    fn label(&self) -> Option<&str> {
+        Some(&self.label)
+    }
+
+    fn update(&mut self, _stash: &mut Stash) -> Option<UpdateRequest> {
+        let wgpu = self.wgpu_handle.get();
+        let sample_count = wgpu.sample_count();
+        if sample_count != self.sample_count {
+            self.pipeline = create_pipeline(
+                &wgpu,
+                &self.label,
+                &self.source_code,
+                &self.fragment_entry,
+                &self.layout,
+                self.target_format,
+                sample_count,
+            );
+            self.sample_count = sample_count;
+        }
+        None
+    }
+
+    fn render(
+        &self,
+        controller: &RenderController<K>,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+        let wgpu = controller.wgpu.get();
+        // every pixel is overwritten, so clearing is only a hint that the old contents can be discarded
+        let mut pass = wgpu.render_pass(
+            Some(&self.label),
+            encoder,
+            target,
+            None,
+            LoadOp::Clear(wgpu::Color::BLACK),
+        );
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, &self.group, &[]);
+        fullscreen::draw(&mut pass);
+    }
+}
+
+fn create_pipeline(
+    wgpu: &WgpuRenderer,
+    label: &str,
+    source_code: &str,
+    fragment_entry: &str,
+    layout: &wgpu::BindGroupLayout,
+    target_format: TextureFormat,
+    sample_count: u32,
+) -> WgpuPipeline {
+    PipelineBuilder::new(wgpu, label)
+        .shader(
+            label,
+            source_code,
+            Some(fullscreen::VERTEX_ENTRY),
+            Some(fragment_entry),
+        )
+        .push_bind_group(layout.clone())
+        .add_color_target(target_format)
+        .with_sample_count(sample_count)
+        .build(None)
+}
+
+fn create_group(
+    wgpu: &WgpuRenderer,
+    label: &str,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    source: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    wgpu.bind_group(
+        Some(label),
+        layout,
+        &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    )
+}
@@ -0,0 +1,543 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::{LoadOp, TextureFormat};
+
+use crate::{
+    component::{ComponentHandle, ComponentStore},
+    graphics::{
+        lowlevel::{WgpuRenderer, buf::UniformBuffer, fullscreen},
+        pipeline::{
+            RenderPipeline, UpdateRequest,
+            controller::{PipelineKey, RenderController, Stash},
+        },
+    },
+};
+
+/// The format of the intermediate bloom textures.
+const BLOOM_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+const BLOOM_SHADER: &str = r#"
+struct BloomParams {
+    threshold: f32,
+    intensity: f32,
+};
+
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: BloomParams;
+@group(0) @binding(3) var bloom: texture_2d<f32>;
+
+fn texel() -> vec2<f32> {
+    return 1.0 / vec2<f32>(textureDimensions(src));
+}
+
+// 4-tap box filter, sampling between texels so each tap averages 4 of them
+fn box4(uv: vec2<f32>) -> vec3<f32> {
+    let d = texel() * 0.5;
+    var color = textureSample(src, src_sampler, uv + vec2<f32>(-d.x, -d.y)).rgb;
+    color += textureSample(src, src_sampler, uv + vec2<f32>(d.x, -d.y)).rgb;
+    color += textureSample(src, src_sampler, uv + vec2<f32>(-d.x, d.y)).rgb;
+    color += textureSample(src, src_sampler, uv + vec2<f32>(d.x, d.y)).rgb;
+    return color * 0.25;
+}
+
+@fragment
+fn bloom_prefilter(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = box4(in.uv);
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - params.threshold, 0.0) / max(brightness, 0.0001);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn bloom_downsample(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(box4(in.uv), 1.0);
+}
+
+// 9-tap tent filter
+@fragment
+fn bloom_upsample(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let d = texel();
+    var color = textureSample(src, src_sampler, in.uv).rgb * 4.0;
+    color += textureSample(src, src_sampler, in.uv + vec2<f32>(-d.x, 0.0)).rgb * 2.0;
+    color += textureSample(src, src_sampler, in.uv + vec2<f32>(d.x, 0.0)).rgb * 2.0;
+    color += textureSample(src, src_sampler, in.uv + vec2<f32>(0.0, -d.y)).rgb * 2.0;
+    color += textureSample(src, src_sampler, in.uv + vec2<f32>(0.0, d.y)).rgb * 2.0;
+    color += textureSample(src, src_sampler, in.uv + vec2<f32>(-d.x, -d.y)).rgb;
+    color += textureSample(src, src_sampler, in.uv + vec2<f32>(d.x, -d.y)).rgb;
+    color += textureSample(src, src_sampler, in.uv + vec2<f32>(-d.x, d.y)).rgb;
+    color += textureSample(src, src_sampler, in.uv + vec2<f32>(d.x, d.y)).rgb;
+    return vec4<f32>(color / 16.0, 1.0);
+}
+
+@fragment
+fn bloom_composite(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(src, src_sampler, in.uv);
+    let glow = textureSample(bloom, src_sampler, in.uv).rgb;
+    return vec4<f32>(color.rgb + glow * params.intensity, color.a);
+}
+"#;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct BloomParams {
+    threshold: f32,
+    intensity: f32,
+    // uniform buffers must be 16 byte aligned
+    _pad: [f32; 2],
+}
+
+/// A bloom post-process pipeline.
+///
+/// The bright parts of an HDR source texture are extracted into a chain of progressively smaller textures,
+/// blurred while upsampling back through the chain, and added on top of the source when rendering to the target.
+///
+/// The source must be a filterable float texture of the size given to `new` (or `resize`).
+///
+/// The composite pass renders with `WgpuRenderer::sample_count`, and is rebuilt in `update` when it changes. The
+/// mip chain is always single sampled.
+pub struct BloomPipeline {
+    /// Brightness above which pixels start to bloom.
+    pub threshold: f32,
+    /// How strongly the bloom is added back onto the source.
+    pub intensity: f32,
+    params: UniformBuffer<BloomParams>,
+    sampler: wgpu::Sampler,
+    sample_layout: wgpu::BindGroupLayout,
+    composite_layout: wgpu::BindGroupLayout,
+    prefilter: wgpu::RenderPipeline,
+    downsample: wgpu::RenderPipeline,
+    upsample: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+    module: wgpu::ShaderModule,
+    target_format: TextureFormat,
+    // the sample count `composite` was built with
+    composite_samples: u32,
+    mip_count: u32,
+    // per mip level: the texture view and a bind group sampling it
+    mips: Vec<(wgpu::TextureView, wgpu::BindGroup)>,
+    // samples the source, used by the prefilter
+    source_group: wgpu::BindGroup,
+    composite_group: wgpu::BindGroup,
+    wgpu_handle: ComponentHandle<WgpuRenderer>,
+}
+
+impl BloomPipeline {
+    /// Creates a new bloom pipeline reading from `source`, which is `size` pixels large.
+    ///
+    /// `target_format` is the format of the texture the pipeline renders to. `mip_count` is the maximum number of
+    /// downsampled levels; more levels give a wider glow. It is limited so that the smallest level is at least 1px.
+    pub fn new(
+        state: &ComponentStore,
+        source: &wgpu::TextureView,
+        size: (u32, u32),
+        target_format: TextureFormat,
+        mip_count: u32,
+    ) -> Self {
+        let wgpu = state.get::<WgpuRenderer>();
+
+        let params = wgpu.uniform_buffer(
+            &BloomParams {
+                threshold: 1.0,
+                intensity: 1.0,
+                _pad: [0.0; 2],
+            },
+            Some("Bloom Params"),
+        );
+        let sampler = wgpu.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Bloom Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let sample_layout = wgpu.bind_group_layout(
+            Some("Bloom Sample Layout"),
+            &bind_group_layout_entries(false),
+        );
+        let composite_layout = wgpu.bind_group_layout(
+            Some("Bloom Composite Layout"),
+            &bind_group_layout_entries(true),
+        );
+
+        let module = wgpu
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Bloom Shader"),
+                source: wgpu::ShaderSource::Wgsl(fullscreen::with_fragment(BLOOM_SHADER).into()),
+            });
+
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+
+        let prefilter = create_pipeline(
+            &wgpu,
+            &module,
+            &sample_layout,
+            "bloom_prefilter",
+            BLOOM_FORMAT,
+            None,
+            1,
+        );
+        let downsample = create_pipeline(
+            &wgpu,
+            &module,
+            &sample_layout,
+            "bloom_downsample",
+            BLOOM_FORMAT,
+            None,
+            1,
+        );
+        let upsample = create_pipeline(
+            &wgpu,
+            &module,
+            &sample_layout,
+            "bloom_upsample",
+            BLOOM_FORMAT,
+            Some(additive),
+            1,
+        );
+        let composite_samples = wgpu.sample_count();
+        let composite = create_pipeline(
+            &wgpu,
+            &module,
+            &composite_layout,
+            "bloom_composite",
+            target_format,
+            None,
+            composite_samples,
+        );
+
+        let (mips, source_group, composite_group) = create_mips(
+            &wgpu,
+            &params,
+            &sampler,
+            &sample_layout,
+            &composite_layout,
+            source,
+            size,
+            mip_count,
+        );
+
+        Self {
+            threshold: 1.0,
+            intensity: 1.0,
+            params,
+            sampler,
+            sample_layout,
+            composite_layout,
+            prefilter,
+            downsample,
+            upsample,
+            composite,
+            module,
+            target_format,
+            composite_samples,
+            mip_count,
+            mips,
+            source_group,
+            composite_group,
+            wgpu_handle: state.handle_for(),
+        }
+    }
+
+    /// Recreates the mip chain for a new source texture of the given size.
+    pub fn resize(&mut self, source: &wgpu::TextureView, size: (u32, u32)) {
+        let wgpu = self.wgpu_handle.get();
+        let (mips, source_group, composite_group) = create_mips(
+            &wgpu,
+            &self.params,
+            &self.sampler,
+            &self.sample_layout,
+            &self.composite_layout,
+            source,
+            size,
+            self.mip_count,
+        );
+        self.mips = mips;
+        self.source_group = source_group;
+        self.composite_group = composite_group;
+    }
+
+    /// Returns the number of levels in the mip chain.
+    pub fn levels(&self) -> usize {
+        self.mips.len()
+    }
+}
+
+impl<K: PipelineKey> RenderPipeline<K> for BloomPipeline {
+    fn label(&self) -> Option<&str> {
+        Some("Bloom Pipeline")
+    }
+
+    fn update(&mut self, _stash: &mut Stash) -> Option<UpdateRequest> {
+        let wgpu = self.wgpu_handle.get();
+        let sample_count = wgpu.sample_count();
+        if sample_count != self.composite_samples {
+            self.composite = create_pipeline(
+                &wgpu,
+                &self.module,
+                &self.composite_layout,
+                "bloom_composite",
+                self.target_format,
+                None,
+                sample_count,
+            );
+            self.composite_samples = sample_count;
+        }
+
+        self.params.write(&BloomParams {
+            threshold: self.threshold,
+            intensity: self.intensity,
+            _pad: [0.0; 2],
+        });
+        None
+    }
+
+    fn render(
+        &self,
+        controller: &RenderController<K>,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+        let wgpu = controller.wgpu.get();
+        let pass = |encoder: &mut wgpu::CommandEncoder,
+                    label: &str,
+                    view: &wgpu::TextureView,
+                    load: LoadOp<wgpu::Color>,
+                    pipeline: &wgpu::RenderPipeline,
+                    group: &wgpu::BindGroup| {
+            let mut pass = wgpu.render_pass(Some(label), encoder, view, None, load);
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, group, &[]);
+            fullscreen::draw(&mut pass);
+        };
+        let clear = LoadOp::Clear(wgpu::Color::BLACK);
+
+        // bright pass into the first level, then down the chain
+        let (first, _) = &self.mips[0];
+        pass(
+            encoder,
+            "Bloom Prefilter",
+            first,
+            clear,
+            &self.prefilter,
+            &self.source_group,
+        );
+        for window in self.mips.windows(2) {
+            let [(_, src_group), (dst, _)] = window else {
+                unreachable!()
+            };
+            pass(
+                encoder,
+                "Bloom Downsample",
+                dst,
+                clear,
+                &self.downsample,
+                src_group,
+            );
+        }
+
+        // blur back up the chain, adding each level onto the next larger one
+        for window in self.mips.windows(2).rev() {
+            let [(dst, _), (_, src_group)] = window else {
+                unreachable!()
+            };
+            pass(
+                encoder,
+                "Bloom Upsample",
+                dst,
+                LoadOp::Load,
+                &self.upsample,
+                src_group,
+            );
+        }
+
+        pass(
+            encoder,
+            "Bloom Composite",
+            target,
+            clear,
+            &self.composite,
+            &self.composite_group,
+        );
+    }
+}
+
+fn bind_group_layout_entries(composite: bool) -> Vec<wgpu::BindGroupLayoutEntry> {
+    let texture = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None,
+    };
+
+    let mut entries = vec![
+        texture(0),
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+    ];
+    if composite {
+        entries.push(texture(3));
+    }
+    entries
+}
+
+fn create_pipeline(
+    wgpu: &WgpuRenderer,
+    module: &wgpu::ShaderModule,
+    layout: &wgpu::BindGroupLayout,
+    entry_point: &str,
+    format: TextureFormat,
+    blend: Option<wgpu::BlendState>,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = wgpu.pipeline_layout(Some(entry_point), &[layout]);
+    wgpu.create_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: Some(fullscreen::VERTEX_ENTRY),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: Some(entry_point),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+type MipChain = (
+    Vec<(wgpu::TextureView, wgpu::BindGroup)>,
+    wgpu::BindGroup,
+    wgpu::BindGroup,
+);
+
+#[allow(clippy::too_many_arguments)]
+fn create_mips(
+    wgpu: &WgpuRenderer,
+    params: &UniformBuffer<BloomParams>,
+    sampler: &wgpu::Sampler,
+    sample_layout: &wgpu::BindGroupLayout,
+    composite_layout: &wgpu::BindGroupLayout,
+    source: &wgpu::TextureView,
+    size: (u32, u32),
+    mip_count: u32,
+) -> MipChain {
+    let sample_group = |view: &wgpu::TextureView| {
+        wgpu.bind_group(
+            Some("Bloom Sample Group"),
+            sample_layout,
+            &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params.buffer().as_entire_binding(),
+                },
+            ],
+        )
+    };
+
+    // the first level is half the size of the source, and no level may be smaller than 1px
+    let (mut width, mut height) = ((size.0 / 2).max(1), (size.1 / 2).max(1));
+    let mut mips = Vec::new();
+    for _ in 0..mip_count.max(1) {
+        let texture = wgpu.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Bloom Mip"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: BLOOM_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let group = sample_group(&view);
+        mips.push((view, group));
+
+        if width == 1 && height == 1 {
+            break;
+        }
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+    }
+
+    let composite_group = wgpu.bind_group(
+        Some("Bloom Composite Group"),
+        composite_layout,
+        &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: params.buffer().as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&mips[0].0),
+            },
+        ],
+    );
+
+    (mips, sample_group(source), composite_group)
+}
@@ -1,10 +1,14 @@
 use wgpu::{Color, LoadOp};
 
 use crate::graphics::pipeline::{
-    RenderPipeline,
+    ClearColor, RenderPipeline,
     controller::{PipelineKey, Stash},
 };
 
+/// Clears the target to a color.
+///
+/// If a `ClearColor` was stashed for the frame (e.g. via `UpdateRequest::SetClearColor`), it is used instead of the
+/// pipeline's own color.
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct ClearPipeline(pub Color);
 
@@ -29,13 +33,16 @@ impl<K: PipelineKey> RenderPipeline<K> for ClearPipeline {
         encoder: &mut wgpu::CommandEncoder,
         target: &wgpu::TextureView,
     ) {
+        let color = controller
+            .retrieve_checked::<ClearColor>()
+            .map_or(self.0, |c| c.0);
         let wgpu = controller.wgpu.get();
         let _render_pass_desc = wgpu.render_pass(
             Some("Clear Pipeline Render Pass"),
             encoder,
             target,
             None,
-            LoadOp::Clear(self.0),
+            LoadOp::Clear(color),
         );
     }
 }
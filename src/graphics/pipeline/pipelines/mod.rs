@@ -1 +1,3 @@
+pub mod blit;
+pub mod bloom;
 pub mod clear;
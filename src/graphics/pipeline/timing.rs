@@ -0,0 +1,155 @@
+//! GPU timestamp queries used by `RenderController` to time each pipeline.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+use parking_lot::Mutex;
+
+use crate::graphics::{lowlevel::WgpuRenderer, pipeline::controller::PipelineKey};
+
+/// The maximum number of pipelines that can be timed in a single frame.
+const MAX_TIMED_PIPELINES: u32 = 64;
+
+// readback states
+const IDLE: u8 = 0;
+const RECORDED: u8 = 1;
+const MAPPING: u8 = 2;
+const MAPPED: u8 = 3;
+
+/// Records a pair of timestamps around each pipeline's render call and reads them back without blocking.
+pub(super) struct GpuTimer<K: PipelineKey> {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    // nanoseconds per timestamp tick
+    period: f32,
+    // keys timed in the frame currently being read back, in query order
+    recorded: Mutex<Vec<K>>,
+    state: Arc<AtomicU8>,
+}
+
+impl<K: PipelineKey> GpuTimer<K> {
+    /// Creates a new GpuTimer, or None if the device does not support timestamp queries inside encoders.
+    pub(super) fn new(wgpu: &WgpuRenderer) -> Option<Self> {
+        let required =
+            wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS;
+        if !wgpu.device.features().contains(required) {
+            return None;
+        }
+
+        let count = MAX_TIMED_PIPELINES * 2;
+        let size = count as u64 * std::mem::size_of::<u64>() as u64;
+
+        let query_set = wgpu.device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("pipeline timing queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count,
+        });
+
+        let resolve_buffer = wgpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pipeline timing resolve buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = wgpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pipeline timing readback buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: wgpu.queue.get_timestamp_period(),
+            recorded: Mutex::new(Vec::new()),
+            state: Arc::new(AtomicU8::new(IDLE)),
+        })
+    }
+
+    /// Starts recording a frame. Returns false if the previous frame is still being read back.
+    pub(super) fn begin(&self) -> bool {
+        if self.state.load(Ordering::Acquire) != IDLE {
+            return false;
+        }
+        self.recorded.lock().clear();
+        true
+    }
+
+    /// Writes the start timestamp for the given pipeline, returning its slot if it could be timed.
+    pub(super) fn start(&self, encoder: &mut wgpu::CommandEncoder, key: &K) -> Option<u32> {
+        let mut recorded = self.recorded.lock();
+        let slot = recorded.len() as u32;
+        if slot >= MAX_TIMED_PIPELINES {
+            return None;
+        }
+        recorded.push(key.clone());
+        encoder.write_timestamp(&self.query_set, slot * 2);
+        Some(slot)
+    }
+
+    /// Writes the end timestamp for the given slot.
+    pub(super) fn end(&self, encoder: &mut wgpu::CommandEncoder, slot: u32) {
+        encoder.write_timestamp(&self.query_set, slot * 2 + 1);
+    }
+
+    /// Resolves the recorded queries into the readback buffer.
+    pub(super) fn finish(&self, encoder: &mut wgpu::CommandEncoder) {
+        let count = self.recorded.lock().len() as u32 * 2;
+        if count == 0 {
+            return;
+        }
+
+        let size = count as u64 * std::mem::size_of::<u64>() as u64;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, size);
+        self.state.store(RECORDED, Ordering::Release);
+    }
+
+    /// Advances the readback. Must only be called after the frame recorded by `finish` was submitted.
+    ///
+    /// Returns the timings in milliseconds once the readback has completed.
+    pub(super) fn collect(&self, wgpu: &WgpuRenderer) -> Option<HashMap<K, f32>> {
+        if self.state.load(Ordering::Acquire) == RECORDED {
+            self.state.store(MAPPING, Ordering::Release);
+            let state = self.state.clone();
+            self.readback_buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    // on failure, drop this frame's timings and start over
+                    let next = if result.is_ok() { MAPPED } else { IDLE };
+                    state.store(next, Ordering::Release);
+                });
+        }
+
+        if self.state.load(Ordering::Acquire) == MAPPING {
+            let _ = wgpu.poll(wgpu::PollType::Poll);
+        }
+
+        if self.state.load(Ordering::Acquire) != MAPPED {
+            return None;
+        }
+
+        let recorded = self.recorded.lock();
+        let mut timings = HashMap::with_capacity(recorded.len());
+        {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let tick = |i: usize| u64::from_le_bytes(data[i * 8..i * 8 + 8].try_into().unwrap());
+            for (i, key) in recorded.iter().enumerate() {
+                let elapsed = tick(i * 2 + 1).saturating_sub(tick(i * 2));
+                timings.insert(key.clone(), elapsed as f32 * self.period / 1_000_000.0);
+            }
+        }
+        self.readback_buffer.unmap();
+        self.state.store(IDLE, Ordering::Release);
+        Some(timings)
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
     component::{ComponentHandle, ComponentStore},
     graphics::{
         image::Image,
-        lowlevel::{WgpuRenderer, texture::Texture},
+        lowlevel::{WgpuRenderer, memory::TrackedAllocation, texture::Texture},
     },
 };
 
@@ -154,3 +154,190 @@ impl TextureHandle {
         self.base_layer + index
     }
 }
+
+/// A cubemap texture, e.g. for skyboxes or environment reflections.
+///
+/// Faces are stored as 6 array layers in wgpu's order: +X, -X, +Y, -Y, +Z, -Z. In terms of
+/// [`CardinalDirection`](crate::graphics::CardinalDirection), that is East, West, Up, Down, South, North.
+#[derive(Debug, Clone)]
+pub struct CubemapTexture {
+    pub texture: wgpu::Texture,
+    /// A view of the texture with `TextureViewDimension::Cube`.
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    size: u32,
+    _allocation: TrackedAllocation,
+    handle: ComponentHandle<WgpuRenderer>,
+}
+
+impl CubemapTexture {
+    /// Creates a cubemap from six square faces of the same size, given in the order +X, -X, +Y, -Y, +Z, -Z.
+    ///
+    /// `format` must be a 4-byte-per-texel format matching the RGBA8 image data, i.e. `Rgba8Unorm` or
+    /// `Rgba8UnormSrgb`.
+    ///
+    /// # Panics
+    /// Panics if the faces are not square or don't all have the same size.
+    pub fn from_faces(
+        state: &ComponentStore,
+        label: Option<&str>,
+        faces: [&Image; 6],
+        format: TextureFormat,
+    ) -> Self {
+        let (size, height) = faces[0].dimensions();
+        assert_eq!(size, height, "Cubemap faces must be square");
+        assert!(
+            faces.iter().all(|face| face.dimensions() == (size, size)),
+            "Cubemap faces must all have the same size"
+        );
+
+        let wgpu = state.get::<WgpuRenderer>();
+        let texture = wgpu.create_texture(&Self::descriptor(label, size, format));
+
+        for (layer, face) in faces.iter().enumerate() {
+            wgpu.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                face.pixel_bytes(),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(size * 4),
+                    rows_per_image: Some(size),
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label,
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = wgpu.create_sampler(&wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            _allocation: TrackedAllocation::texture(&texture),
+            texture,
+            view,
+            sampler,
+            size,
+            handle: state.handle_for::<WgpuRenderer>(),
+        }
+    }
+
+    /// Returns the texture descriptor used for a cubemap with faces of `size` x `size` texels.
+    fn descriptor(
+        label: Option<&str>,
+        size: u32,
+        format: TextureFormat,
+    ) -> wgpu::TextureDescriptor<'_> {
+        wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        }
+    }
+
+    /// Returns the width and height of each face, in texels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Creates a bind group layout with the cube texture and its sampler. Sample it as a `texture_cube<f32>`.
+    pub fn bind_group_layout(
+        &self,
+        texture_binding: u32,
+        sampler_binding: u32,
+    ) -> wgpu::BindGroupLayout {
+        self.handle.get().bind_group_layout(
+            Some("Cubemap Layout"),
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: texture_binding,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: sampler_binding,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        )
+    }
+
+    /// Creates a bind group for the layout from `bind_group_layout`.
+    pub fn bind_group(
+        &self,
+        layout: &wgpu::BindGroupLayout,
+        texture_binding: u32,
+        sampler_binding: u32,
+    ) -> wgpu::BindGroup {
+        self.handle.get().bind_group(
+            Some("Cubemap Bind Group"),
+            layout,
+            &[
+                wgpu::BindGroupEntry {
+                    binding: texture_binding,
+                    resource: wgpu::BindingResource::TextureView(&self.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: sampler_binding,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu::TextureFormat;
+
+    use super::CubemapTexture;
+
+    #[test]
+    fn test_cubemap_descriptor() {
+        let desc = CubemapTexture::descriptor(None, 256, TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(desc.size.width, 256);
+        assert_eq!(desc.size.height, 256);
+        assert_eq!(desc.size.depth_or_array_layers, 6);
+        assert_eq!(desc.dimension, wgpu::TextureDimension::D2);
+        assert_eq!(desc.array_layer_count(), 6);
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use glam::{Mat4, Vec2, Vec3, vec2};
+use glam::{Mat4, Vec2, Vec3, Vec4, vec2};
 use winit::keyboard::{Key, KeyCode};
 
 use crate::{
@@ -8,16 +8,105 @@ use crate::{
     graphics::{
         callback::TargetHandle,
         camera::Camera,
-        lowlevel::{WgpuRenderer, buf::UniformBuffer},
+        lowlevel::{
+            WgpuRenderer,
+            buf::{Tracked, UniformBuffer},
+        },
     },
+    input::{keyboard::Keyboard, map::InputMap, mouse::Mouse},
 };
 
+/// Radial dead-zone applied to gamepad sticks in `CameraController::update_with_gamepad`.
+const GAMEPAD_DEAD_ZONE: f32 = 0.15;
+/// The largest pitch magnitude allowed, in radians. At ±90° the front is parallel to up and there is no right vector.
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.001;
+
+/// Zeroes stick input inside `dead_zone` and rescales the remainder to `0..=1`.
+fn apply_dead_zone(axis: Vec2, dead_zone: f32) -> Vec2 {
+    let magnitude = axis.length();
+    if magnitude <= dead_zone {
+        return Vec2::ZERO;
+    }
+    let scaled = ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    axis / magnitude * scaled
+}
+
+/// Key bindings used by `CameraController` for movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CameraBindings {
+    /// Moves the camera along its front vector.
+    pub forward: KeyCode,
+    /// Moves the camera against its front vector.
+    pub back: KeyCode,
+    /// Strafes the camera left.
+    pub left: KeyCode,
+    /// Strafes the camera right.
+    pub right: KeyCode,
+    /// Moves the camera along its up vector.
+    pub up: KeyCode,
+    /// Moves the camera against its up vector.
+    pub down: KeyCode,
+}
+
+/// Movement actions of `CameraController`, for use with an [`InputMap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CameraAction {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl From<CameraBindings> for InputMap<CameraAction> {
+    fn from(bindings: CameraBindings) -> Self {
+        let mut map = InputMap::new();
+        map.bind(CameraAction::Forward, bindings.forward)
+            .bind(CameraAction::Back, bindings.back)
+            .bind(CameraAction::Left, bindings.left)
+            .bind(CameraAction::Right, bindings.right)
+            .bind(CameraAction::Up, bindings.up)
+            .bind(CameraAction::Down, bindings.down);
+        map
+    }
+}
+
+impl Default for CameraBindings {
+    fn default() -> Self {
+        Self {
+            forward: KeyCode::KeyW,
+            back: KeyCode::KeyS,
+            left: KeyCode::KeyA,
+            right: KeyCode::KeyD,
+            up: KeyCode::Space,
+            down: KeyCode::ShiftLeft,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct CameraController {
     /// Mouse sensitivity.
     pub sensitivity: f32,
+    /// Vertical mouse sensitivity. If None, `sensitivity` is used for both axes.
+    pub sensitivity_y: Option<f32>,
+    /// Inverts the vertical look direction.
+    pub invert_y: bool,
+    /// Movement speed, in units per second.
+    pub speed: f32,
+    /// How quickly the camera reaches its movement speed, in units per second squared.
+    pub acceleration: f32,
+    /// How quickly the camera slows down when no movement keys are held. Higher values stop faster.
+    pub friction: f32,
+    velocity: Vec3,
+    // (min, max) pitch in radians
+    pitch_limits: (f32, f32),
+    bindings: CameraBindings,
     camera: Camera,
     uniform: UniformBuffer<Mat4>,
+    // the matrix in `uniform`, so stationary frames can skip the upload
+    matrix: Tracked<Mat4>,
     wgpu_handle: ComponentHandle<WgpuRenderer>,
 }
 
@@ -43,12 +132,22 @@ impl CameraController {
         let (width, height) = dimensions;
         let camera = Camera::new(width as f32 / height as f32, z_near, z_far);
 
-        let uniform = wgpu.uniform_buffer(&camera.projection_view_matrix(), Some("Camera Uniform"));
+        let matrix = camera.projection_view_matrix();
+        let uniform = wgpu.uniform_buffer(&matrix, Some("Camera Uniform"));
         CameraController {
             wgpu_handle: state.handle_for::<WgpuRenderer>(),
             camera,
             uniform,
+            matrix: Tracked::clean(matrix),
             sensitivity: 0.1,
+            sensitivity_y: None,
+            invert_y: false,
+            speed: 10.0,
+            acceleration: 80.0,
+            friction: 10.0,
+            velocity: Vec3::ZERO,
+            pitch_limits: (-89.0_f32.to_radians(), 89.0_f32.to_radians()),
+            bindings: CameraBindings::default(),
         }
     }
 
@@ -74,10 +173,22 @@ impl CameraController {
         )
     }
 
-    /// Writes the current camera matrix to the uniform buffer.
+    /// Writes the current camera matrix to the uniform buffer, if it changed since the last flush.
     pub fn flush(&mut self) {
-        let matrix = self.camera.projection_view_matrix();
-        self.uniform.write(&matrix);
+        self.matrix.set(self.camera.projection_view_matrix());
+        self.matrix.flush_if_dirty(&self.uniform);
+    }
+
+    /// Returns the combined projection and view matrix of the camera.
+    ///
+    /// This is the same matrix that `flush` writes to the uniform buffer, and can be reused by other passes.
+    pub fn view_projection(&self) -> Mat4 {
+        self.camera.projection_view_matrix()
+    }
+
+    /// Returns a copy of the camera reflected across the given plane. See [`Camera::mirrored`].
+    pub fn mirrored(&self, plane: Vec4) -> Camera {
+        self.camera.mirrored(plane)
     }
 
     /// Sets the camera to look at a specific target point.
@@ -108,42 +219,159 @@ impl CameraController {
 
     /// Updates the camera rotation based on mouse movement.
     pub fn update_with_mouse_coords(&mut self, mouse_delta: Vec2, delta_time: f64) {
-        let delta = mouse_delta * self.sensitivity * delta_time as f32;
+        self.apply_look(mouse_delta, delta_time);
+    }
+
+    /// Rotates the camera by the given look input, applying sensitivity, inversion, and the pitch clamp.
+    fn apply_look(&mut self, look: Vec2, delta_time: f64) {
+        let sensitivity = vec2(
+            self.sensitivity,
+            self.sensitivity_y.unwrap_or(self.sensitivity),
+        );
+        let mut delta = look * sensitivity * delta_time as f32;
+        if self.invert_y {
+            delta.y = -delta.y;
+        }
+
+        self.camera.rotate_clamped(delta, self.pitch_limits);
+    }
 
-        self.camera.rot += delta;
+    /// Returns the (min, max) pitch limits, in radians.
+    pub fn pitch_limits(&self) -> (f32, f32) {
+        self.pitch_limits
+    }
+
+    /// Sets the (min, max) pitch limits, in radians.
+    ///
+    /// Both limits are clamped to just inside ±90°, as looking straight up or down leaves no horizontal direction to
+    /// move in.
+    ///
+    /// # Panics
+    /// Panics if `min > max`.
+    pub fn set_pitch_limits(&mut self, min: f32, max: f32) {
+        assert!(min <= max, "Pitch limits min > max");
+        self.pitch_limits = (
+            min.clamp(-MAX_PITCH, MAX_PITCH),
+            max.clamp(-MAX_PITCH, MAX_PITCH),
+        );
+    }
 
-        // Clamp the pitch to avoid flipping. rot is in radians.
-        self.camera.rot.y = self
-            .camera
-            .rot
-            .y
-            .clamp(-89.0_f32.to_radians(), 89.0_f32.to_radians());
+    /// Updates the camera using gamepad sticks.
+    ///
+    /// `move_axis` acts like the movement keys, with the stick magnitude scaling the speed. `look_axis` acts like mouse
+    /// movement and is scaled by the sensitivity. Both sticks have a small radial dead-zone applied.
+    pub fn update_with_gamepad(&mut self, move_axis: Vec2, look_axis: Vec2, delta_time: f64) {
+        let move_axis = apply_dead_zone(move_axis, GAMEPAD_DEAD_ZONE);
+        let look_axis = apply_dead_zone(look_axis, GAMEPAD_DEAD_ZONE);
 
-        self.camera.flush();
+        self.apply_look(look_axis, delta_time);
+
+        let front = self.camera.front();
+        let right = front.cross(self.camera.up()).normalize_or_zero();
+        let input = front * move_axis.y + right * move_axis.x;
+        self.apply_movement(input.clamp_length_max(1.0), delta_time as f32);
+        self.flush();
     }
 
     /// Updates the camera position based on keyboard input.
-    pub fn update_camera(&mut self, keyboard: &crate::input::keyboard::Keyboard, delta_time: f64) {
-        let speed = 10.0 * delta_time as f32;
+    pub fn update_camera(&mut self, keyboard: &Keyboard, delta_time: f64) {
+        let bindings = self.bindings;
+        self.update_with_actions(
+            |action| {
+                keyboard.is_key_down(match action {
+                    CameraAction::Forward => bindings.forward,
+                    CameraAction::Back => bindings.back,
+                    CameraAction::Left => bindings.left,
+                    CameraAction::Right => bindings.right,
+                    CameraAction::Up => bindings.up,
+                    CameraAction::Down => bindings.down,
+                })
+            },
+            delta_time,
+        );
+    }
+
+    /// Updates the camera position using the actions held in `map`, instead of the controller's own bindings.
+    pub fn update_with_input_map(
+        &mut self,
+        map: &InputMap<CameraAction>,
+        keyboard: &Keyboard,
+        mouse: &Mouse,
+        delta_time: f64,
+    ) {
+        self.update_with_actions(
+            |action| map.is_action_held(&action, keyboard, mouse),
+            delta_time,
+        );
+    }
+
+    fn update_with_actions(&mut self, held: impl Fn(CameraAction) -> bool, delta_time: f64) {
         let front = self.camera.front();
-        if keyboard.is_key_held(KeyCode::KeyW) {
-            self.update_position(|c| c + front * speed);
+        let up = self.camera.up();
+        let right = front.cross(up).normalize_or_zero();
+
+        let mut input = Vec3::ZERO;
+        if held(CameraAction::Forward) {
+            input += front;
+        }
+        if held(CameraAction::Back) {
+            input -= front;
         }
-        if keyboard.is_key_held(KeyCode::KeyS) {
-            self.update_position(|c| c - front * speed);
+        if held(CameraAction::Left) {
+            input -= right;
         }
-        if keyboard.is_key_held(KeyCode::KeyA) {
-            let right = front.cross(Vec3::Y).normalize();
-            self.update_position(|c| c - right * speed);
+        if held(CameraAction::Right) {
+            input += right;
         }
-        if keyboard.is_key_held(KeyCode::KeyD) {
-            let right = front.cross(Vec3::Y).normalize();
-            self.update_position(|c| c + right * speed);
+        if held(CameraAction::Up) {
+            input += up;
+        }
+        if held(CameraAction::Down) {
+            input -= up;
         }
 
+        self.apply_movement(input.normalize_or_zero(), delta_time as f32);
         self.flush();
     }
 
+    /// Accelerates the velocity towards `input * speed` and integrates it into the camera position.
+    ///
+    /// `input` should have a length of at most 1. When it is zero, the velocity decays according to `friction`.
+    fn apply_movement(&mut self, input: Vec3, delta_time: f32) {
+        if input != Vec3::ZERO {
+            let target = input * self.speed;
+            let delta = target - self.velocity;
+            let max_step = self.acceleration * delta_time;
+            if delta.length() <= max_step {
+                self.velocity = target;
+            } else {
+                self.velocity += delta.normalize() * max_step;
+            }
+        } else {
+            self.velocity *= (-self.friction * delta_time).exp();
+        }
+
+        let velocity = self.velocity;
+        if velocity != Vec3::ZERO {
+            self.update_position(|c| c + velocity * delta_time);
+        }
+    }
+
+    /// Returns the current movement velocity of the camera.
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    /// Returns the key bindings used for movement.
+    pub fn bindings(&self) -> &CameraBindings {
+        &self.bindings
+    }
+
+    /// Replaces the key bindings used for movement.
+    pub fn set_bindings(&mut self, bindings: CameraBindings) {
+        self.bindings = bindings;
+    }
+
     /// Sets the position of the camera.
     pub fn update_position(&mut self, f: impl FnOnce(Vec3) -> Vec3) {
         let new = f(self.camera.position);
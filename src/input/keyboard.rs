@@ -1,10 +1,21 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
-use winit::keyboard::KeyCode;
+use winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
 
-#[derive(Debug)]
+/// Tracks which keys are down, along with the keys that went down or up during the current frame.
+///
+/// Call `end_frame` once per frame after handling input to clear the per-frame state.
+#[derive(Debug, Default)]
 pub struct Keyboard {
-    states: HashMap<KeyCode, KeyState>,
+    down: HashSet<KeyCode>,
+    // edges since the last `end_frame`. a key tapped within one frame is in both sets.
+    pressed: HashSet<KeyCode>,
+    released: HashSet<KeyCode>,
+    // text typed since the last `end_frame`, including auto-repeats
+    text: String,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -22,44 +33,206 @@ pub enum KeyState {
 
 impl Keyboard {
     pub fn new() -> Self {
-        Self {
-            states: HashMap::new(),
-        }
+        Self::default()
     }
 
     pub fn set_key_state(&mut self, key: KeyCode, state: KeyState) {
-        self.states.insert(key, state);
+        self.pressed.remove(&key);
+        self.released.remove(&key);
+        match state {
+            KeyState::Up => {
+                self.down.remove(&key);
+            }
+            KeyState::Pressed => {
+                self.down.insert(key);
+                self.pressed.insert(key);
+            }
+            KeyState::Released => {
+                self.down.remove(&key);
+                self.released.insert(key);
+            }
+            KeyState::Held => {
+                self.down.insert(key);
+            }
+        }
     }
 
+    /// Returns the state of the key, or None if the key is up.
+    ///
+    /// Use `key_state` to get `KeyState::Up` instead of None.
     pub fn get_key_state(&self, key: KeyCode) -> Option<KeyState> {
-        self.states.get(&key).copied()
+        Some(self.key_state(key)).filter(|state| *state != KeyState::Up)
     }
 
-    /// Returns true if the key was pressed this frame.
+    /// Returns the state of the key.
+    pub fn key_state(&self, key: KeyCode) -> KeyState {
+        if self.down.contains(&key) {
+            if self.pressed.contains(&key) {
+                KeyState::Pressed
+            } else {
+                KeyState::Held
+            }
+        } else if self.released.contains(&key) {
+            KeyState::Released
+        } else {
+            KeyState::Up
+        }
+    }
+
+    /// Returns true if the key went down this frame.
+    ///
+    /// This is also true for a key that was pressed and released within the same frame.
     pub fn is_key_pressed(&self, key: KeyCode) -> bool {
-        matches!(self.get_key_state(key), Some(KeyState::Pressed))
+        self.pressed.contains(&key)
+    }
+
+    /// Returns true if the key went up this frame.
+    pub fn is_key_released(&self, key: KeyCode) -> bool {
+        self.released.contains(&key)
     }
 
-    /// Returns true if the key is currently being held down.
+    /// Returns true if the key is being held down, i.e. it was pressed in a previous frame and has not been released.
+    ///
+    /// This is false on the frame the key was pressed. Use `is_key_down` to include that frame.
     pub fn is_key_held(&self, key: KeyCode) -> bool {
-        matches!(self.get_key_state(key), Some(KeyState::Held))
+        self.key_state(key) == KeyState::Held
+    }
+
+    /// Returns true if the key is currently down, including on the frame it was pressed.
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.down.contains(&key)
+    }
+
+    /// Updates the keyboard from a winit key event.
+    ///
+    /// OS auto-repeat events don't change the key state, so `is_key_pressed` is only true on the initial press.
+    /// The text they produce is still collected into `text_input`.
+    pub fn handle_key_event(&mut self, event: &KeyEvent) {
+        let PhysicalKey::Code(key) = event.physical_key else {
+            return;
+        };
+        let text = match event.state {
+            ElementState::Pressed => event.text.as_deref(),
+            ElementState::Released => None,
+        };
+        self.handle_key(key, event.state, event.repeat, text);
+    }
+
+    /// Updates the keyboard from the parts of a key event. See `handle_key_event`.
+    pub fn handle_key(
+        &mut self,
+        key: KeyCode,
+        state: ElementState,
+        repeat: bool,
+        text: Option<&str>,
+    ) {
+        if let Some(text) = text {
+            self.text.push_str(text);
+        }
+
+        if repeat {
+            return;
+        }
+
+        match state {
+            ElementState::Pressed => self.press_key(key),
+            ElementState::Released => self.release_key(key),
+        }
+    }
+
+    /// Returns the text typed since the last `end_frame`, including text from auto-repeated keys.
+    pub fn text_input(&self) -> &str {
+        &self.text
     }
 
     pub fn press_key(&mut self, key: KeyCode) {
-        self.set_key_state(key, KeyState::Pressed);
+        if self.down.insert(key) {
+            self.pressed.insert(key);
+        }
     }
 
     pub fn release_key(&mut self, key: KeyCode) {
-        self.set_key_state(key, KeyState::Released);
+        if self.down.remove(&key) {
+            self.released.insert(key);
+        }
     }
 
+    /// Advances to the next frame, clearing the pressed and released edges and the typed text.
+    pub fn end_frame(&mut self) {
+        self.text.clear();
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    /// Advances to the next frame. See `end_frame`.
+    #[deprecated(note = "use `end_frame` instead")]
     pub fn update_keys(&mut self) {
-        for state in self.states.values_mut() {
-            if *state == KeyState::Pressed {
-                *state = KeyState::Held;
-            } else if *state == KeyState::Released {
-                *state = KeyState::Up;
-            }
+        self.end_frame();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{event::ElementState, keyboard::KeyCode};
+
+    use super::{KeyState, Keyboard};
+
+    #[test]
+    fn test_key_repeat_is_filtered() {
+        let mut keyboard = Keyboard::new();
+        let mut presses = 0;
+
+        keyboard.handle_key(KeyCode::KeyA, ElementState::Pressed, false, Some("a"));
+        for _ in 0..4 {
+            presses += keyboard.is_key_pressed(KeyCode::KeyA) as u32;
+            keyboard.end_frame();
+            keyboard.handle_key(KeyCode::KeyA, ElementState::Pressed, true, Some("a"));
+            assert_eq!(keyboard.text_input(), "a");
         }
+        presses += keyboard.is_key_pressed(KeyCode::KeyA) as u32;
+
+        assert_eq!(presses, 1);
+        assert!(keyboard.is_key_held(KeyCode::KeyA));
+
+        keyboard.handle_key(KeyCode::KeyA, ElementState::Released, false, None);
+        assert!(!keyboard.is_key_held(KeyCode::KeyA));
+    }
+
+    #[test]
+    fn test_key_edges_last_one_frame() {
+        let mut keyboard = Keyboard::new();
+
+        keyboard.press_key(KeyCode::Space);
+        assert!(keyboard.is_key_pressed(KeyCode::Space));
+        assert!(keyboard.is_key_down(KeyCode::Space));
+        assert!(!keyboard.is_key_held(KeyCode::Space));
+        assert_eq!(keyboard.key_state(KeyCode::Space), KeyState::Pressed);
+
+        keyboard.end_frame();
+        assert!(!keyboard.is_key_pressed(KeyCode::Space));
+        assert!(keyboard.is_key_held(KeyCode::Space));
+        assert_eq!(keyboard.get_key_state(KeyCode::Space), Some(KeyState::Held));
+        assert_eq!(keyboard.key_state(KeyCode::Space), KeyState::Held);
+
+        keyboard.release_key(KeyCode::Space);
+        assert!(keyboard.is_key_released(KeyCode::Space));
+        assert!(!keyboard.is_key_held(KeyCode::Space));
+        assert!(!keyboard.is_key_down(KeyCode::Space));
+
+        keyboard.end_frame();
+        assert!(!keyboard.is_key_released(KeyCode::Space));
+        assert_eq!(keyboard.key_state(KeyCode::Space), KeyState::Up);
+        assert_eq!(keyboard.get_key_state(KeyCode::Space), None);
+    }
+
+    #[test]
+    fn test_tap_within_frame_is_seen() {
+        let mut keyboard = Keyboard::new();
+
+        keyboard.press_key(KeyCode::Enter);
+        keyboard.release_key(KeyCode::Enter);
+        assert!(keyboard.is_key_pressed(KeyCode::Enter));
+        assert!(keyboard.is_key_released(KeyCode::Enter));
+        assert!(!keyboard.is_key_held(KeyCode::Enter));
     }
 }
@@ -0,0 +1,159 @@
+use std::{collections::HashMap, hash::Hash};
+
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::input::{keyboard::Keyboard, mouse::Mouse};
+
+/// A physical input that can trigger an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl From<KeyCode> for Binding {
+    fn from(key: KeyCode) -> Self {
+        Binding::Key(key)
+    }
+}
+
+impl From<MouseButton> for Binding {
+    fn from(button: MouseButton) -> Self {
+        Binding::Mouse(button)
+    }
+}
+
+impl Binding {
+    fn is_held(self, keyboard: &Keyboard, mouse: &Mouse) -> bool {
+        match self {
+            Binding::Key(key) => keyboard.is_key_down(key),
+            Binding::Mouse(button) => mouse.is_button_held(button),
+        }
+    }
+
+    fn is_pressed(self, keyboard: &Keyboard, mouse: &Mouse) -> bool {
+        match self {
+            Binding::Key(key) => keyboard.is_key_pressed(key),
+            Binding::Mouse(button) => mouse.is_button_pressed(button),
+        }
+    }
+
+    fn is_released(self, keyboard: &Keyboard, mouse: &Mouse) -> bool {
+        match self {
+            Binding::Key(key) => keyboard.is_key_released(key),
+            Binding::Mouse(button) => mouse.is_button_released(button),
+        }
+    }
+}
+
+/// Maps logical actions to the keys and mouse buttons that trigger them.
+///
+/// An action can have any number of bindings, and is active if any of them are.
+#[derive(Debug, Clone)]
+pub struct InputMap<A> {
+    bindings: HashMap<A, Vec<Binding>>,
+}
+
+impl<A> Default for InputMap<A> {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+}
+
+impl<A: Hash + Eq> InputMap<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding to `action`. Binding the same input to an action twice has no effect.
+    pub fn bind(&mut self, action: A, binding: impl Into<Binding>) -> &mut Self {
+        let binding = binding.into();
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+
+    /// Removes a binding from `action`. Returns true if the binding was present.
+    pub fn unbind(&mut self, action: &A, binding: impl Into<Binding>) -> bool {
+        let binding = binding.into();
+        let Some(bindings) = self.bindings.get_mut(action) else {
+            return false;
+        };
+        let len = bindings.len();
+        bindings.retain(|b| *b != binding);
+        len != bindings.len()
+    }
+
+    /// Removes every binding from `action`.
+    pub fn clear(&mut self, action: &A) {
+        self.bindings.remove(action);
+    }
+
+    /// Returns the bindings of `action`, in the order they were added.
+    pub fn bindings(&self, action: &A) -> &[Binding] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns true if any binding of `action` is down, including on the frame it was pressed.
+    pub fn is_action_held(&self, action: &A, keyboard: &Keyboard, mouse: &Mouse) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|b| b.is_held(keyboard, mouse))
+    }
+
+    /// Returns true if any binding of `action` went down this frame.
+    pub fn is_action_pressed(&self, action: &A, keyboard: &Keyboard, mouse: &Mouse) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|b| b.is_pressed(keyboard, mouse))
+    }
+
+    /// Returns true if any binding of `action` went up this frame.
+    pub fn is_action_released(&self, action: &A, keyboard: &Keyboard, mouse: &Mouse) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|b| b.is_released(keyboard, mouse))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{event::MouseButton, keyboard::KeyCode};
+
+    use super::InputMap;
+    use crate::input::{keyboard::Keyboard, mouse::Mouse};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Action {
+        Forward,
+        Fire,
+    }
+
+    #[test]
+    fn test_bindings_or_together() {
+        let mut map = InputMap::new();
+        map.bind(Action::Forward, KeyCode::KeyW)
+            .bind(Action::Forward, KeyCode::ArrowUp)
+            .bind(Action::Fire, MouseButton::Left);
+
+        let mut keyboard = Keyboard::new();
+        let mut mouse = Mouse::new();
+        assert!(!map.is_action_held(&Action::Forward, &keyboard, &mouse));
+
+        keyboard.press_key(KeyCode::ArrowUp);
+        assert!(map.is_action_held(&Action::Forward, &keyboard, &mouse));
+        assert!(map.is_action_pressed(&Action::Forward, &keyboard, &mouse));
+        assert!(!map.is_action_held(&Action::Fire, &keyboard, &mouse));
+
+        mouse.press_button(MouseButton::Left);
+        assert!(map.is_action_pressed(&Action::Fire, &keyboard, &mouse));
+
+        assert!(map.unbind(&Action::Forward, KeyCode::ArrowUp));
+        assert!(!map.is_action_held(&Action::Forward, &keyboard, &mouse));
+        assert_eq!(map.bindings(&Action::Forward).len(), 1);
+    }
+}
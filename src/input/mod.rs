@@ -1,2 +1,5 @@
 pub mod camera;
 pub mod keyboard;
+pub mod map;
+pub mod mouse;
+pub mod touch;
@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use glam::{Vec2, vec2};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+
+/// How many pixels of a pixel-based scroll (e.g. a touchpad) count as one line.
+pub const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Tracks mouse buttons, the cursor position and the scroll wheel.
+///
+/// Like `Keyboard`, call `end_frame` once per frame after handling input to clear the per-frame state.
+#[derive(Debug, Default)]
+pub struct Mouse {
+    down: HashSet<MouseButton>,
+    // edges since the last `end_frame`. a button clicked within one frame is in both sets.
+    pressed: HashSet<MouseButton>,
+    released: HashSet<MouseButton>,
+    position: Option<Vec2>,
+    // the position at the last `end_frame`
+    previous: Option<Vec2>,
+    scroll: Vec2,
+}
+
+impl Mouse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the mouse from a winit window event. Returns true if the event was a mouse event.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.move_cursor(vec2(position.x as f32, position.y as f32))
+            }
+            WindowEvent::CursorLeft { .. } => self.leave(),
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => self.press_button(*button),
+                ElementState::Released => self.release_button(*button),
+            },
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => self.scroll(vec2(*x, *y)),
+                MouseScrollDelta::PixelDelta(delta) => {
+                    self.scroll(vec2(delta.x as f32, delta.y as f32) / PIXELS_PER_LINE)
+                }
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn press_button(&mut self, button: MouseButton) {
+        if self.down.insert(button) {
+            self.pressed.insert(button);
+        }
+    }
+
+    pub fn release_button(&mut self, button: MouseButton) {
+        if self.down.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    /// Moves the cursor to `position`, in physical pixels relative to the top left of the window.
+    pub fn move_cursor(&mut self, position: Vec2) {
+        self.position = Some(position);
+    }
+
+    /// Marks the cursor as having left the window.
+    pub fn leave(&mut self) {
+        self.position = None;
+        self.previous = None;
+    }
+
+    /// Adds to the scroll accumulated this frame, in lines.
+    pub fn scroll(&mut self, delta: Vec2) {
+        self.scroll += delta;
+    }
+
+    /// Returns true if the button went down this frame.
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Returns true if the button went up this frame.
+    pub fn is_button_released(&self, button: MouseButton) -> bool {
+        self.released.contains(&button)
+    }
+
+    /// Returns true if the button is currently down, including on the frame it was pressed.
+    pub fn is_button_held(&self, button: MouseButton) -> bool {
+        self.down.contains(&button)
+    }
+
+    /// Returns the cursor position in physical pixels, or `None` if the cursor is outside the window.
+    pub fn position(&self) -> Option<Vec2> {
+        self.position
+    }
+
+    /// Returns how far the cursor moved since the last `end_frame`, in physical pixels.
+    ///
+    /// This is zero on the frame the cursor enters the window.
+    pub fn cursor_delta(&self) -> Vec2 {
+        match (self.position, self.previous) {
+            (Some(position), Some(previous)) => position - previous,
+            _ => Vec2::ZERO,
+        }
+    }
+
+    /// Returns the scroll accumulated since the last `end_frame`, in lines. Positive y scrolls up.
+    pub fn scroll_delta(&self) -> Vec2 {
+        self.scroll
+    }
+
+    /// Advances to the next frame, clearing the button edges, cursor delta and scroll.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.previous = self.position;
+        self.scroll = Vec2::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+    use winit::event::MouseButton;
+
+    use super::Mouse;
+
+    #[test]
+    fn test_cursor_and_scroll_reset_each_frame() {
+        let mut mouse = Mouse::new();
+
+        mouse.move_cursor(vec2(10.0, 10.0));
+        assert_eq!(mouse.cursor_delta(), vec2(0.0, 0.0));
+        mouse.end_frame();
+
+        mouse.move_cursor(vec2(15.0, 12.0));
+        mouse.move_cursor(vec2(20.0, 14.0));
+        mouse.scroll(vec2(0.0, 1.0));
+        mouse.scroll(vec2(0.0, 0.5));
+        assert_eq!(mouse.cursor_delta(), vec2(10.0, 4.0));
+        assert_eq!(mouse.scroll_delta(), vec2(0.0, 1.5));
+
+        mouse.end_frame();
+        assert_eq!(mouse.cursor_delta(), vec2(0.0, 0.0));
+        assert_eq!(mouse.scroll_delta(), vec2(0.0, 0.0));
+        assert_eq!(mouse.position(), Some(vec2(20.0, 14.0)));
+    }
+
+    #[test]
+    fn test_button_edges() {
+        let mut mouse = Mouse::new();
+
+        mouse.press_button(MouseButton::Right);
+        assert!(mouse.is_button_pressed(MouseButton::Right));
+        assert!(mouse.is_button_held(MouseButton::Right));
+
+        mouse.end_frame();
+        assert!(!mouse.is_button_pressed(MouseButton::Right));
+        assert!(mouse.is_button_held(MouseButton::Right));
+
+        mouse.release_button(MouseButton::Right);
+        assert!(mouse.is_button_released(MouseButton::Right));
+        assert!(!mouse.is_button_held(MouseButton::Right));
+    }
+}
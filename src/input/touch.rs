@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use glam::{Vec2, vec2};
+use winit::event::TouchPhase;
+
+/// A single active touch point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchPoint {
+    /// Where the touch started.
+    pub start: Vec2,
+    /// The position at the last `update_touches`.
+    pub previous: Vec2,
+    /// The current position.
+    pub position: Vec2,
+}
+
+/// Tracks active touch points by id, and recognizes pinch and pan gestures between frames.
+#[derive(Debug, Default)]
+pub struct Touch {
+    points: HashMap<u64, TouchPoint>,
+}
+
+impl Touch {
+    pub fn new() -> Self {
+        Self {
+            points: HashMap::new(),
+        }
+    }
+
+    /// Updates the touch points from a winit touch event.
+    pub fn handle_touch(&mut self, touch: &winit::event::Touch) {
+        let position = vec2(touch.location.x as f32, touch.location.y as f32);
+        match touch.phase {
+            TouchPhase::Started => self.touch_start(touch.id, position),
+            TouchPhase::Moved => self.touch_move(touch.id, position),
+            TouchPhase::Ended | TouchPhase::Cancelled => self.touch_end(touch.id),
+        }
+    }
+
+    /// Starts tracking a touch point.
+    pub fn touch_start(&mut self, id: u64, position: Vec2) {
+        self.points.insert(
+            id,
+            TouchPoint {
+                start: position,
+                previous: position,
+                position,
+            },
+        );
+    }
+
+    /// Moves a touch point. Unknown ids are treated as new touches.
+    pub fn touch_move(&mut self, id: u64, position: Vec2) {
+        match self.points.get_mut(&id) {
+            Some(point) => point.position = position,
+            None => self.touch_start(id, position),
+        }
+    }
+
+    /// Stops tracking a touch point.
+    pub fn touch_end(&mut self, id: u64) {
+        self.points.remove(&id);
+    }
+
+    /// Returns the touch point with the given id, if it is active.
+    pub fn point(&self, id: u64) -> Option<&TouchPoint> {
+        self.points.get(&id)
+    }
+
+    /// Returns the number of active touch points.
+    pub fn count(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns the change in distance between two fingers since the last `update_touches`, in pixels.
+    ///
+    /// Positive values mean the fingers moved apart. This is 0 unless exactly two fingers are down.
+    pub fn pinch_delta(&self) -> f32 {
+        let mut points = self.points.values();
+        let (Some(a), Some(b), None) = (points.next(), points.next(), points.next()) else {
+            return 0.0;
+        };
+
+        a.position.distance(b.position) - a.previous.distance(b.previous)
+    }
+
+    /// Returns how far the centroid of all touch points moved since the last `update_touches`, in pixels.
+    pub fn pan_delta(&self) -> Vec2 {
+        if self.points.is_empty() {
+            return Vec2::ZERO;
+        }
+
+        let (current, previous) = self
+            .points
+            .values()
+            .fold((Vec2::ZERO, Vec2::ZERO), |(current, previous), point| {
+                (current + point.position, previous + point.previous)
+            });
+        (current - previous) / self.points.len() as f32
+    }
+
+    /// Marks the current positions as the baseline for the next frame's gestures.
+    pub fn update_touches(&mut self) {
+        for point in self.points.values_mut() {
+            point.previous = point.position;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::Touch;
+
+    #[test]
+    fn test_pinch_delta() {
+        let mut touch = Touch::new();
+        touch.touch_start(0, vec2(100.0, 100.0));
+        touch.touch_start(1, vec2(200.0, 100.0));
+        touch.update_touches();
+        assert_eq!(touch.pinch_delta(), 0.0);
+
+        touch.touch_move(0, vec2(80.0, 100.0));
+        touch.touch_move(1, vec2(230.0, 100.0));
+        assert!((touch.pinch_delta() - 50.0).abs() < 1e-4);
+        assert!((touch.pan_delta() - vec2(5.0, 0.0)).length() < 1e-4);
+
+        touch.update_touches();
+        touch.touch_end(1);
+        assert_eq!(touch.count(), 1);
+        assert_eq!(touch.pinch_delta(), 0.0);
+        assert_eq!(touch.pan_delta(), vec2(0.0, 0.0));
+    }
+}
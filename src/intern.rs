@@ -0,0 +1,65 @@
+use std::collections::HashSet;
+
+use parking_lot::Mutex;
+use rustc_hash::FxBuildHasher;
+
+use crate::ReadOnlyString;
+
+/// A thread-safe string interner.
+///
+/// Interning the same string twice returns pointer-equal `ReadOnlyString`s, so interned strings can be compared cheaply
+/// with `Arc::ptr_eq`.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: Mutex<HashSet<ReadOnlyString, FxBuildHasher>>,
+}
+
+impl Interner {
+    /// Creates a new, empty Interner.
+    pub fn new() -> Self {
+        Self {
+            strings: Default::default(),
+        }
+    }
+
+    /// Interns the given string, returning a shared handle to it.
+    pub fn intern(&self, string: &str) -> ReadOnlyString {
+        let mut strings = self.strings.lock();
+        if let Some(existing) = strings.get(string) {
+            return existing.clone();
+        }
+
+        let interned: ReadOnlyString = string.into();
+        strings.insert(interned.clone());
+        interned
+    }
+
+    /// Returns the number of unique strings interned.
+    pub fn len(&self) -> usize {
+        self.strings.lock().len()
+    }
+
+    /// Returns true if no strings have been interned.
+    pub fn is_empty(&self) -> bool {
+        self.strings.lock().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::Interner;
+
+    #[test]
+    fn test_intern_ptr_eq() {
+        let interner = Interner::new();
+        let a = interner.intern("kiwi:grass");
+        let b = interner.intern(&String::from("kiwi:grass"));
+        let c = interner.intern("kiwi:dirt");
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(interner.len(), 2);
+    }
+}
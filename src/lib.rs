@@ -1,4 +1,4 @@
-#![feature(thread_id_value)]
+#![cfg_attr(feature = "nightly", feature(thread_id_value))]
 use std::sync::Arc;
 
 use glam::Vec3;
@@ -26,6 +26,7 @@ pub mod assets;
 pub mod component;
 pub mod graphics;
 pub mod input;
+pub mod intern;
 pub mod shared;
 pub mod prelude {
     pub use crate::FloatPosition;
@@ -46,12 +47,14 @@ pub mod prelude {
         CardinalDirection,
         camera::Camera as RawCamera,
         lowlevel::{
-            WgpuRenderer,
+            SurfaceError, WgpuRenderer,
             buf::{IndexBuffer, IndexLayout, UniformBuffer, VertexBuffer, VertexLayout},
             pipeline::{PipelineBuilder, WgpuPipeline},
-            shader::ShaderProgram,
+            shader::{ShaderError, ShaderProgram},
         },
+        mesh::Mesh,
     };
     pub use crate::input::*;
+    pub use crate::intern::Interner;
     pub use crate::shared::*;
 }
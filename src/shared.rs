@@ -1,4 +1,6 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 /// A shared resource wrapper that provides interior mutability.
 #[derive(Debug, PartialEq, Eq, Default)]
@@ -23,6 +25,28 @@ impl<T> Shared<T> {
         self.inner.borrow_mut()
     }
 
+    /// Borrows the resource immutably, returning None if it is currently mutably borrowed.
+    pub fn try_get(&self) -> Option<std::cell::Ref<'_, T>> {
+        self.inner.try_borrow().ok()
+    }
+
+    /// Borrows the resource mutably, returning None if it is currently borrowed.
+    pub fn try_get_mut(&self) -> Option<std::cell::RefMut<'_, T>> {
+        self.inner.try_borrow_mut().ok()
+    }
+
+    /// Borrows part of the resource immutably, e.g. `state.map(|s| &s.player)`.
+    ///
+    /// The resource stays borrowed for as long as the returned `Ref` is alive. See `Ref::map`.
+    pub fn map<U: ?Sized>(&self, f: impl FnOnce(&T) -> &U) -> std::cell::Ref<'_, U> {
+        std::cell::Ref::map(self.get(), f)
+    }
+
+    /// Borrows part of the resource mutably. See `map` and `RefMut::map`.
+    pub fn map_mut<U: ?Sized>(&self, f: impl FnOnce(&mut T) -> &mut U) -> std::cell::RefMut<'_, U> {
+        std::cell::RefMut::map(self.get_mut(), f)
+    }
+
     /// Creates a new cyclic Resource.
     ///
     /// This was primarily added for GameState to hold a Weak reference to itself.
@@ -76,3 +100,80 @@ impl<T> WeakShared<T> {
         self.inner.upgrade().map(|rc| Shared { inner: rc })
     }
 }
+
+/// A thread-safe version of [`Shared`], backed by an `Arc<RwLock<T>>`.
+///
+/// Use `Shared` for single-threaded state, and this when the state needs to be sent across threads.
+#[derive(Debug, Default)]
+pub struct SharedSync<T> {
+    pub inner: Arc<RwLock<T>>,
+}
+
+impl<T> SharedSync<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(value)),
+        }
+    }
+
+    /// Locks the resource for reading.
+    pub fn get(&self) -> RwLockReadGuard<'_, T> {
+        self.inner.read()
+    }
+
+    /// Locks the resource for writing.
+    pub fn get_mut(&self) -> RwLockWriteGuard<'_, T> {
+        self.inner.write()
+    }
+
+    /// Creates a new cyclic resource. See [`Shared::new_cyclic`].
+    pub fn new_cyclic(value: impl FnOnce(WeakSharedSync<T>) -> T) -> Self {
+        let arc = Arc::new_cyclic(|weak| {
+            RwLock::new(value(WeakSharedSync {
+                inner: weak.clone(),
+            }))
+        });
+        Self { inner: arc }
+    }
+
+    /// Downgrades the resource to a weak reference.
+    pub fn downgrade(&self) -> WeakSharedSync<T> {
+        WeakSharedSync {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+}
+
+impl<T> From<T> for SharedSync<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T> Clone for SharedSync<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+/// A weak reference to a SharedSync pointer.
+#[derive(Debug)]
+pub struct WeakSharedSync<T> {
+    inner: std::sync::Weak<RwLock<T>>,
+}
+
+impl<T> Clone for WeakSharedSync<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> WeakSharedSync<T> {
+    pub fn upgrade(&self) -> Option<SharedSync<T>> {
+        self.inner.upgrade().map(|arc| SharedSync { inner: arc })
+    }
+}